@@ -1,7 +1,471 @@
+import { mkdtempSync, readFileSync } from 'fs'
+import { tmpdir } from 'os'
+import { join } from 'path'
+import { deflateSync } from 'zlib'
+
 import test from 'ava'
 
-import { sum } from '../index.js'
+import {
+  sum,
+  buildCompositedImage,
+  buildCompositedImageAsync,
+  buildCompositedImageWithInfo,
+  buildCompositedImageWithInfoAsync,
+  composeLayers,
+  composeLayersAsync,
+} from '../index.js'
+
+const product = readFileSync(new URL('../resources/product.jpg', import.meta.url))
+const overlay = readFileSync(new URL('../resources/overlay.png', import.meta.url))
+// 40x20 pixels, tagged with EXIF orientation 6 (rotate 90 degrees clockwise).
+const rotated = readFileSync(new URL('../resources/orientation-6.jpg', import.meta.url))
+// 4x4 pixels of rgb(50, 150, 220) tagged with an Adobe RGB profile.
+const adobeRgb = readFileSync(new URL('../resources/adobe-rgb.png', import.meta.url))
+// 8x8 pixels, an opaque white 4x4 square on transparent black.
+const cutout = readFileSync(new URL('../resources/cutout.png', import.meta.url))
+// 4x4 pixels of full cyan ink, tagged with a CMYK profile.
+const cmyk = readFileSync(new URL('../resources/cmyk.jpg', import.meta.url))
+
+const crcTable = Array.from({ length: 256 }, (_, n) => {
+  for (let bit = 0; bit < 8; bit++) {
+    n = n & 1 ? 0xedb88320 ^ (n >>> 1) : n >>> 1
+  }
+  return n >>> 0
+})
+const crc32 = (bytes) => ~bytes.reduce((crc, byte) => crcTable[(crc ^ byte) & 0xff] ^ (crc >>> 8), ~0) >>> 0
+const chunk = (type, data) => {
+  const body = Buffer.concat([Buffer.from(type), data])
+  const framed = Buffer.alloc(body.length + 8)
+  framed.writeUInt32BE(data.length)
+  body.copy(framed, 4)
+  framed.writeUInt32BE(crc32(body), body.length + 4)
+  return framed
+}
+// A `width` x `height` RGBA PNG filled with `color`, encoded here rather than
+// by the library under test.
+const solid = (width, height, color) => {
+  const header = Buffer.alloc(13)
+  header.writeUInt32BE(width)
+  header.writeUInt32BE(height, 4)
+  header.set([8, 6, 0, 0, 0], 8)
+  const row = [0, ...Array.from({ length: width }, () => color).flat()]
+  const pixels = Buffer.from(Array.from({ length: height }, () => row).flat())
+  return Buffer.concat([
+    Buffer.from([0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]),
+    chunk('IHDR', header),
+    chunk('IDAT', deflateSync(pixels)),
+    chunk('IEND', Buffer.alloc(0)),
+  ])
+}
 
 test('sum from native', (t) => {
   t.is(sum(1, 2), 3)
 })
+
+test('buildCompositedImage returns an encoded PNG buffer', (t) => {
+  const result = buildCompositedImage(product, overlay, { backgroundColor: [0, 0, 255, 255] })
+  t.true(Buffer.isBuffer(result))
+  t.is(result.subarray(1, 4).toString(), 'PNG')
+})
+
+test('buildCompositedImage writes the image to outputPath', (t) => {
+  const dir = mkdtempSync(join(tmpdir(), 'resharper-'))
+  const options = { backgroundColor: [0, 0, 255, 255], outputPath: join(dir, 'out.png') }
+  const result = buildCompositedImage(product, overlay, options)
+  t.deepEqual(readFileSync(options.outputPath), result)
+  t.throws(() => buildCompositedImage(product, overlay, { ...options, outputPath: join(dir, 'missing', 'out.png') }), {
+    code: 'WRITE_OUTPUT_FAILED',
+  })
+})
+
+test('buildCompositedImage reports typed error codes', (t) => {
+  t.throws(() => buildCompositedImage(Buffer.from('nope'), overlay, { backgroundColor: [0, 0, 255, 255] }), {
+    code: 'DECODE_PRODUCT_FAILED',
+  })
+  t.throws(() => buildCompositedImage(product, overlay, { backgroundColor: [0, 0] }), {
+    code: 'INVALID_COLOR',
+  })
+})
+
+test('buildCompositedImageAsync resolves to the same image as the sync call', async (t) => {
+  const options = { backgroundColor: [0, 0, 255, 255] }
+  const result = await buildCompositedImageAsync(product, overlay, options)
+  t.deepEqual(result, buildCompositedImage(product, overlay, options))
+  await t.throwsAsync(buildCompositedImageAsync(product, overlay, { backgroundColor: [0] }), {
+    code: 'INVALID_COLOR',
+  })
+})
+
+test('async calls reject when aborted', async (t) => {
+  const options = { backgroundColor: [0, 0, 255, 255] }
+  const calls = [
+    (signal) => buildCompositedImageAsync(product, overlay, options, signal),
+    (signal) => buildCompositedImageWithInfoAsync(product, overlay, options, signal),
+    (signal) => composeLayersAsync({ width: 10, height: 10 }, [{ buffer: overlay }], {}, signal),
+  ]
+  for (const call of calls) {
+    const controller = new AbortController()
+    const promise = call(controller.signal)
+    controller.abort()
+    await t.throwsAsync(promise, { code: 'Cancelled', message: 'AbortError' })
+  }
+  t.true(Buffer.isBuffer(await calls[0]()))
+})
+
+test('buildCompositedImage encodes the requested output format', (t) => {
+  const jpeg = buildCompositedImage(product, overlay, {
+    backgroundColor: [0, 0, 255, 255],
+    output: { format: 'Jpeg', quality: 70, progressive: true },
+  })
+  t.deepEqual([...jpeg.subarray(0, 2)], [0xff, 0xd8])
+
+  const webp = buildCompositedImage(product, overlay, {
+    backgroundColor: [0, 0, 255, 255],
+    output: { format: 'Webp', quality: 70 },
+  })
+  t.is(webp.subarray(8, 12).toString(), 'WEBP')
+})
+
+test('buildCompositedImage rejects malformed offsets', (t) => {
+  const cases = [
+    ['Pixel', [10]],
+    ['Pixel', [Number.NaN, 0]],
+    ['Pixel', [0, Number.POSITIVE_INFINITY]],
+    ['Pixel', [-1e300, 0]],
+    ['Percent', [1e300, 0]],
+  ]
+  for (const [type, value] of cases) {
+    t.throws(
+      () => buildCompositedImage(product, overlay, { backgroundColor: [0, 0, 255, 255], offsetMode: { type, value } }),
+      { code: 'INVALID_OFFSET_MODE' },
+    )
+  }
+})
+
+test('clamp keeps the layer inside the canvas, or covering it when larger', (t) => {
+  const backgroundColor = [0, 0, 0, 255]
+  const white = solid(2, 2, [255, 255, 255, 255])
+  // Offsets are the layer's center on a black 10x10 canvas.
+  const place = (layer, value, clamp) =>
+    buildCompositedImage(layer, solid(10, 10, [0, 0, 0, 0]), { backgroundColor, offsetMode: { type: 'Pixel', value, clamp } })
+  const black = place(white, [-5, -5])
+
+  t.deepEqual(place(white, [-5, 20], true), place(white, [1, 9]))
+  t.deepEqual(place(white, [-5, 20], false), black)
+  t.deepEqual(place(white, [4, 5], true), place(white, [4, 5]))
+  t.deepEqual(place(white, [2 ** 31 - 1, -(2 ** 31 - 1)], true), place(white, [9, 1]))
+  t.deepEqual(place(white, [2 ** 31 - 1, -(2 ** 31 - 1)], false), black)
+
+  // 14x14 with the white marker in its top-left corner.
+  const large = buildCompositedImage(white, solid(14, 14, [0, 0, 0, 0]), {
+    backgroundColor,
+    offsetMode: { type: 'Pixel', value: [1, 1] },
+  })
+  t.deepEqual(place(large, [12, 12], true), place(white, [1, 1]))
+  t.deepEqual(place(large, [-10, -10], true), black)
+  t.deepEqual(place(large, [6, 8], true), place(white, [0, 1]))
+})
+
+test('offsets place the layer anchor, centering it by default', (t) => {
+  const backgroundColor = [0, 0, 0, 255]
+  const white = (size) => solid(size, size, [255, 255, 255, 255])
+  const clear = (size) => solid(size, size, [0, 0, 0, 0])
+  // A white product on a black canvas the size of the overlay.
+  const productAt = (size, offsetMode) => buildCompositedImage(white(size), clear(6), { backgroundColor, offsetMode })
+
+  const topLeft = productAt(2, { type: 'Pixel', value: [2, 2], anchor: 'TopLeft' })
+  t.deepEqual(productAt(2, { type: 'Pixel', value: [4, 4], anchor: 'BottomRight' }), topLeft)
+  t.deepEqual(productAt(2, { type: 'Pixel', value: [3, 3] }), topLeft)
+  t.deepEqual(productAt(2, { type: 'Percent', value: [50, 50] }), topLeft)
+  t.deepEqual(productAt(2, { type: 'Center' }), topLeft)
+  t.notDeepEqual(productAt(2, { type: 'Pixel', value: [0, 0], anchor: 'TopLeft' }), topLeft)
+
+  // A white overlay over a clear product lands where a white product would.
+  const overlayAt = (overlayOffsetMode) => buildCompositedImage(clear(1), white(6), { backgroundColor, overlayOffsetMode })
+  for (const offsetMode of [
+    { type: 'Pixel', value: [1, 2], anchor: 'TopLeft' },
+    { type: 'Pixel', value: [7, 8], anchor: 'BottomRight' },
+    { type: 'Pixel', value: [4, 5] },
+  ]) {
+    t.deepEqual(overlayAt(offsetMode), productAt(6, offsetMode))
+  }
+  t.deepEqual(overlayAt(undefined), productAt(6, undefined))
+  t.notDeepEqual(overlayAt(undefined), overlayAt({ type: 'Pixel', value: [4, 5] }))
+})
+
+test('buildCompositedImage renders onto an explicit canvas', (t) => {
+  const result = buildCompositedImage(product, overlay, {
+    backgroundColor: [0, 0, 255, 255],
+    canvas: { width: 1200, height: 1200 },
+  })
+  t.is(result.readUInt32BE(16), 1200)
+  t.is(result.readUInt32BE(20), 1200)
+  for (const width of [0, -1, 1.5]) {
+    t.throws(() => buildCompositedImage(product, overlay, { backgroundColor: [0, 0, 255, 255], canvas: { width, height: 10 } }), {
+      code: 'INVALID_CANVAS',
+    })
+  }
+})
+
+test('composeLayers stacks an arbitrary number of layers', (t) => {
+  const result = composeLayers({ width: 1000, height: 700 }, [
+    { buffer: product, resizeMode: { type: 'Width', value: 300 }, offsetMode: { type: 'Percent', value: [30, 50] } },
+    { buffer: product, resizeMode: { type: 'Width', value: 300 }, offsetMode: { type: 'Percent', value: [70, 50] }, opacity: 0.5 },
+    { buffer: overlay },
+  ])
+  t.is(result.readUInt32BE(16), 1000)
+  t.is(result.readUInt32BE(20), 700)
+  t.throws(() => composeLayers({ width: 10, height: 10 }, [{ buffer: product, opacity: 2 }]), {
+    code: 'INVALID_OPACITY',
+  })
+})
+
+test('composeLayers applies blend modes', (t) => {
+  const pixel = (backgroundColor) => composeLayers({ width: 1, height: 1, backgroundColor }, [])
+  const blend = (blendMode, source, backdrop) =>
+    composeLayers({ width: 1, height: 1, backgroundColor: backdrop }, [{ buffer: solid(1, 1, source), blendMode }])
+  const expected = {
+    Multiply: [41, 61, 41, 255],
+    Screen: [214, 194, 214, 255],
+    Overlay: [82, 122, 173, 255],
+    SoftLight: [89, 114, 180, 255],
+    Darken: [51, 102, 51, 255],
+    Lighten: [204, 153, 204, 255],
+    Difference: [153, 51, 153, 255],
+    Additive: [255, 255, 255, 255],
+  }
+  for (const [blendMode, color] of Object.entries(expected)) {
+    t.deepEqual(blend(blendMode, [204, 153, 51, 255], [51, 102, 204, 255]), pixel(color), blendMode)
+  }
+
+  // A half-transparent source is mixed in by its alpha, and blends with nothing
+  // where the backdrop is transparent.
+  t.deepEqual(blend('Normal', [255, 255, 255, 128], [0, 0, 0, 255]), pixel([128, 128, 128, 255]))
+  t.deepEqual(blend('Multiply', [204, 153, 51, 128], [0, 0, 0, 0]), pixel([204, 153, 51, 128]))
+})
+
+test('product and overlay opacity fade the layer', (t) => {
+  const backgroundColor = [0, 0, 0, 255]
+  const white = solid(1, 1, [255, 255, 255, 255])
+  const clear = solid(1, 1, [0, 0, 0, 0])
+  const gray = (value) => composeLayers({ width: 1, height: 1, backgroundColor: [value, value, value, 255] }, [])
+  t.deepEqual(buildCompositedImage(white, clear, { backgroundColor, product: { opacity: 0.5 } }), gray(128))
+  t.deepEqual(buildCompositedImage(clear, white, { backgroundColor, overlay: { opacity: 0.25 } }), gray(64))
+  t.deepEqual(buildCompositedImage(white, clear, { backgroundColor, product: { opacity: 0 } }), gray(0))
+  t.throws(() => buildCompositedImage(white, clear, { backgroundColor, overlay: { opacity: -0.1 } }), {
+    code: 'INVALID_OPACITY',
+  })
+})
+
+test('resize modes produce the expected dimensions', (t) => {
+  const white = (width, height) => solid(width, height, [255, 255, 255, 255])
+  // The layer drawn at the top-left corner of a black canvas shows its size.
+  const footprint = (buffer, resizeMode) =>
+    composeLayers({ width: 130, height: 130, backgroundColor: [0, 0, 0, 255] }, [
+      { buffer, resizeMode, offsetMode: { type: 'Pixel', value: [0, 0], anchor: 'TopLeft' } },
+    ])
+  const wide = white(40, 20)
+  const cases = [
+    [wide, { type: 'Fit', width: 10, height: 10 }, [10, 5]],
+    [white(1000, 1), { type: 'Fit', width: 10, height: 10 }, [10, 1]],
+    [wide, { type: 'Cover', width: 10, height: 10 }, [10, 10]],
+    [white(3, 7), { type: 'Cover', width: 51, height: 119 }, [51, 119]],
+    [wide, { type: 'Exact', width: 7, height: 9 }, [7, 9]],
+    [wide, { type: 'Max', width: 100, height: 100 }, [40, 20]],
+    [wide, { type: 'Max', width: 20, height: 20 }, [20, 10]],
+    [white(1, 1000), { type: 'Max', width: 20, height: 20 }, [1, 20]],
+  ]
+  for (const [buffer, resizeMode, [width, height]] of cases) {
+    t.deepEqual(footprint(buffer, resizeMode), footprint(white(width, height)), JSON.stringify(resizeMode))
+  }
+})
+
+test('resize filters are applied, Auto picking cheaper kernels on large downscales', (t) => {
+  const resize = (buffer, value, filter) =>
+    composeLayers({ width: value, height: value }, [{ buffer, resizeMode: { type: 'Width', value, filter } }])
+  // 400px down to 40px is a 0.1 factor.
+  t.deepEqual(resize(product, 40, 'Auto'), resize(product, 40, 'Triangle'))
+  t.notDeepEqual(resize(product, 40, 'Auto'), resize(product, 40, 'Lanczos3'))
+  t.deepEqual(resize(product, 120, 'Auto'), resize(product, 120, 'CatmullRom'))
+  t.deepEqual(resize(product, 300, 'Auto'), resize(product, 300, 'Lanczos3'))
+
+  const small = resize(product, 8, 'Triangle')
+  t.notDeepEqual(resize(small, 20, 'Nearest'), resize(small, 20, 'Lanczos3'))
+})
+
+test('buildCompositedImage guards resize dimensions', (t) => {
+  const options = (resizeMode, limits) => ({ backgroundColor: [0, 0, 255, 255], resizeMode, limits })
+  for (const value of [0, -1, Number.NaN]) {
+    t.throws(() => buildCompositedImage(product, overlay, options({ type: 'Scale', value })), {
+      code: 'INVALID_RESIZE_MODE',
+    })
+  }
+  t.throws(() => buildCompositedImage(product, overlay, options({ type: 'Scale', value: 0.001 })), {
+    code: 'INVALID_RESIZE_MODE',
+  })
+  for (const width of [-1, 10.5]) {
+    t.throws(() => buildCompositedImage(product, overlay, options({ type: 'Exact', width, height: 10 })), {
+      code: 'INVALID_RESIZE_MODE',
+      message: `resizeMode.width must be a whole number of pixels between 1 and 4294967295, got ${width}`,
+    })
+  }
+  t.throws(() => buildCompositedImage(product, overlay, options({ type: 'Scale', value: 1000 })), {
+    code: 'LIMIT_EXCEEDED',
+  })
+  t.throws(
+    () => buildCompositedImage(product, overlay, options({ type: 'Scale', value: 2 }, { maxOutputPixels: 500000 })),
+    { code: 'LIMIT_EXCEEDED' },
+  )
+})
+
+test('buildCompositedImage enforces decode limits', (t) => {
+  const options = (limits) => ({ backgroundColor: [0, 0, 255, 255], limits })
+  t.throws(() => buildCompositedImage(product, overlay, options({ maxWidth: 10 })), { code: 'INPUT_TOO_LARGE' })
+  t.throws(() => buildCompositedImage(product, overlay, options({ maxHeight: 10 })), { code: 'INPUT_TOO_LARGE' })
+  t.throws(() => buildCompositedImage(product, overlay, options({ maxAllocBytes: 1024 })), { code: 'INPUT_TOO_LARGE' })
+  t.true(buildCompositedImage(product, overlay, options({ maxWidth: 10000, maxHeight: 10000 })).length > 0)
+})
+
+test('buildCompositedImage removes the product background', (t) => {
+  const options = (product) => ({ backgroundColor: [0, 0, 255, 255], product })
+  const plain = buildCompositedImage(product, overlay, options())
+  const hidden = buildCompositedImage(product, overlay, options({ opacity: 0 }))
+  const removed = buildCompositedImage(product, overlay, options({ removeBackground: { mode: 'Global', tolerance: 255 } }))
+  t.deepEqual(removed, hidden)
+  t.notDeepEqual(buildCompositedImage(product, overlay, options({ removeBackground: {} })), plain)
+  t.throws(() => buildCompositedImage(product, overlay, options({ removeBackground: { feather: -1 } })), {
+    code: 'INVALID_REMOVE_BACKGROUND',
+  })
+})
+
+test('buildCompositedImageWithInfo reports the auto-trimmed product box', async (t) => {
+  const options = { backgroundColor: [0, 0, 255, 255], product: { autoTrim: {} } }
+  const info = buildCompositedImageWithInfo(product, overlay, options)
+  t.deepEqual(info.buffer, buildCompositedImage(product, overlay, options))
+  t.true(info.productTrim.width < 400 && info.productTrim.height < 400)
+  t.true(info.productTrim.x > 0 && info.productTrim.y > 0)
+  t.deepEqual((await buildCompositedImageWithInfoAsync(product, overlay, options)).productTrim, info.productTrim)
+  t.is(buildCompositedImageWithInfo(product, overlay, { backgroundColor: [0, 0, 255, 255] }).productTrim, undefined)
+  t.throws(() => buildCompositedImage(product, overlay, { ...options, product: { autoTrim: { tolerance: 300 } } }), {
+    code: 'INVALID_AUTO_TRIM',
+  })
+  t.throws(() => buildCompositedImage(product, overlay, { ...options, product: { autoTrim: { tolerance: -1 } } }), {
+    code: 'INVALID_AUTO_TRIM',
+    message: 'product.autoTrim.tolerance must be a whole number between 0 and 255, got -1',
+  })
+})
+
+test('buildCompositedImage draws a product shadow', (t) => {
+  const options = (product) => ({ backgroundColor: [0, 0, 255, 255], product: { removeBackground: {}, ...product } })
+  const plain = buildCompositedImage(product, overlay, options())
+  t.deepEqual(buildCompositedImage(product, overlay, options({ shadow: { opacity: 0 } })), plain)
+  t.notDeepEqual(buildCompositedImage(product, overlay, options({ shadow: { offsetX: 10, offsetY: 10 } })), plain)
+  t.throws(() => buildCompositedImage(product, overlay, options({ shadow: { blurRadius: 1000 } })), {
+    code: 'INVALID_SHADOW',
+  })
+})
+
+test('backgrounds can be solid, gradients or images', (t) => {
+  const filled = buildCompositedImage(product, overlay, { background: { type: 'Solid', color: [0, 0, 255, 255] } })
+  t.deepEqual(filled, buildCompositedImage(product, overlay, { backgroundColor: [0, 0, 255, 255] }))
+
+  const stops = [
+    { offset: 0, color: [255, 0, 0, 255] },
+    { offset: 1, color: [0, 0, 255, 255] },
+  ]
+  const canvas = (background) => ({ width: 64, height: 32, background })
+  const linear = composeLayers(canvas({ type: 'LinearGradient', stops, angle: 90 }), [])
+  t.notDeepEqual(linear, composeLayers(canvas({ type: 'LinearGradient', stops, angle: 270 }), []))
+  t.notDeepEqual(linear, composeLayers(canvas({ type: 'RadialGradient', stops }), []))
+
+  const image = composeLayers(canvas({ type: 'Image', buffer: product, fit: 'Contain' }), [])
+  t.is(image.readUInt32BE(16), 64)
+  const cover = { type: 'Image', buffer: solid(3, 7, [255, 255, 255, 255]), fit: 'Cover', color: [0, 0, 0, 255] }
+  t.deepEqual(
+    composeLayers({ width: 51, height: 119, background: cover }, []),
+    composeLayers({ width: 51, height: 119, backgroundColor: [255, 255, 255, 255] }, []),
+  )
+  t.throws(() => composeLayers(canvas({ type: 'LinearGradient' }), []), { code: 'INVALID_BACKGROUND' })
+  t.throws(() => composeLayers(canvas({ type: 'Image', buffer: Buffer.from('nope') }), []), {
+    code: 'DECODE_BACKGROUND_FAILED',
+  })
+})
+
+test('colors accept arrays, hex, CSS functions and names', (t) => {
+  const pixel = (backgroundColor) => composeLayers({ width: 1, height: 1, backgroundColor }, [])
+  const blue = pixel([0, 0, 255, 255])
+  for (const color of [[0, 0, 255], '#00f', '#0000FF', '#0000ffff', 'rgb(0, 0, 255)', 'rgba(0 0 255 / 100%)', 'hsl(240, 100%, 50%)', 'Blue']) {
+    t.deepEqual(pixel(color), blue)
+  }
+  t.deepEqual(pixel('transparent'), pixel([0, 0, 0, 0]))
+  for (const color of ['blu', '#12345', 'rgb(0, 0)', 'rgb(256, 0, 0)', 'hsl(0, 100%)', [0], [300, 0, 0], [-1, 0, 0], [0.5, 0, 0]]) {
+    t.throws(() => pixel(color), { code: 'INVALID_COLOR' })
+  }
+})
+
+test('EXIF orientation is applied unless turned off', (t) => {
+  const size = (image) => [image.readUInt32BE(16), image.readUInt32BE(20)]
+  t.deepEqual(size(buildCompositedImage(product, rotated, {})), [20, 40])
+  t.deepEqual(size(buildCompositedImage(product, rotated, { autoOrient: false })), [40, 20])
+})
+
+test('ICC profiles are converted to sRGB and can be embedded', (t) => {
+  const canvas = { width: 4, height: 4 }
+  const filled = (backgroundColor) => composeLayers({ ...canvas, backgroundColor }, [])
+  t.deepEqual(composeLayers(canvas, [{ buffer: adobeRgb }]), filled([0, 151, 223]))
+  t.deepEqual(composeLayers(canvas, [{ buffer: cmyk }]), filled([0, 174, 239]))
+
+  const has = (image, marker) => image.includes(Buffer.from(marker))
+  for (const [format, marker] of [['Png', 'iCCP'], ['Jpeg', 'ICC_PROFILE'], ['Webp', 'ICCP']]) {
+    t.false(has(buildCompositedImage(product, overlay, { output: { format } }), marker))
+    t.true(has(buildCompositedImage(product, overlay, { output: { format, iccProfile: true } }), marker))
+  }
+})
+
+test('linearLight resizes and blends in linear light', (t) => {
+  const white = solid(1, 2, [255, 255, 255, 255])
+  const stripes = composeLayers({ width: 2, height: 2, backgroundColor: 'black' }, [
+    { buffer: white, offsetMode: { type: 'Pixel', value: [1, 0], anchor: 'TopLeft' } },
+  ])
+  const pixel = (backgroundColor) => composeLayers({ width: 1, height: 1, backgroundColor }, [])
+  const downscale = (linearLight) =>
+    composeLayers({ width: 1, height: 1 }, [{ buffer: stripes, resizeMode: { type: 'Width', value: 1 } }], { linearLight })
+  const halfWhite = (linearLight) =>
+    composeLayers({ width: 1, height: 1, backgroundColor: 'black' }, [{ buffer: white, opacity: 0.5 }], { linearLight })
+
+  t.deepEqual(downscale(false), pixel([128, 128, 128]))
+  t.deepEqual(halfWhite(false), pixel([128, 128, 128]))
+  t.deepEqual(downscale(true), pixel([188, 188, 188]))
+  t.deepEqual(halfWhite(true), pixel([188, 188, 188]))
+  t.deepEqual(
+    buildCompositedImage(solid(1, 2, [0, 0, 0, 255]), white, {
+      overlay: { opacity: 0.5 },
+      linearLight: true,
+    }),
+    composeLayers({ width: 1, height: 2, backgroundColor: [188, 188, 188] }, []),
+  )
+})
+
+test('resizing transparent images leaves no dark fringes', (t) => {
+  const canvas = { width: 4, height: 4, backgroundColor: 'white' }
+  const plain = composeLayers(canvas, [])
+  for (const filter of ['Triangle', 'CatmullRom', 'Lanczos3']) {
+    for (const linearLight of [false, true]) {
+      const layer = { buffer: cutout, resizeMode: { type: 'Width', value: 4, filter } }
+      t.deepEqual(composeLayers(canvas, [layer], { linearLight }), plain)
+    }
+  }
+})
+
+test('the product can be rotated and flipped', (t) => {
+  const build = (product, autoOrient = false) => buildCompositedImage(rotated, overlay, { autoOrient, product })
+  // EXIF orientation 6 is a 90 degree clockwise turn.
+  t.deepEqual(build({ rotate: 90 }), build({}, true))
+  t.deepEqual(build({ rotate: -270 }), build({ rotate: 90 }))
+  t.deepEqual(build({ flipH: true, flipV: true }), build({ rotate: 180 }))
+  t.deepEqual(build({ rotate: 360 }), build({}))
+  t.notDeepEqual(build({ flipH: true }), build({}))
+  t.notDeepEqual(build({ rotate: 15 }), build({ rotate: -15 }))
+
+  t.throws(() => build({ rotate: NaN }), { code: 'INVALID_TRANSFORM' })
+})
@@ -1,7 +1,13 @@
 import * as fs from 'fs';
 import * as path from 'path';
 
-import {buildCompositedImage, BuildCompositedImageOptions} from './index';
+import {
+    buildCompositedImage,
+    BuildCompositedImageOptions,
+    OffsetModeType,
+    OutputFormat,
+    ResizeModeType
+} from './index';
 
 const productImagePath: string = path.join(__dirname, './resources/product.jpg');
 const productImageBuffer: Buffer = fs.readFileSync(productImagePath);
@@ -12,13 +18,19 @@ const overlayImageBuffer: Buffer = fs.readFileSync(overlayImagePath);
 const options: BuildCompositedImageOptions = {
     backgroundColor: [0, 0, 255, 255],
     resizeMode: {
-        type: 'Scale',
+        type: ResizeModeType.Scale,
         value: 1
     },
     offsetMode: {
-        type: 'Percent',
+        type: OffsetModeType.Percent,
         value: [50, 50]
-    }
+    },
+    output: {
+        format: OutputFormat.Jpeg,
+        quality: 85
+    },
+    outputPath: './result.jpg'
 };
 
-buildCompositedImage(productImageBuffer, overlayImageBuffer, options);
+const result: Buffer = buildCompositedImage(productImageBuffer, overlayImageBuffer, options);
+console.log(`Composited image: ${result.length} bytes`);
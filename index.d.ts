@@ -3,10 +3,277 @@
 
 /* auto-generated by NAPI-RS */
 
+export const enum BlendMode {
+  Normal = 'Normal',
+  Multiply = 'Multiply',
+  Screen = 'Screen',
+  Overlay = 'Overlay',
+  SoftLight = 'SoftLight',
+  Darken = 'Darken',
+  Lighten = 'Lighten',
+  Difference = 'Difference',
+  Additive = 'Additive'
+}
+export const enum OutputFormat {
+  Png = 'Png',
+  Jpeg = 'Jpeg',
+  Webp = 'Webp',
+  Avif = 'Avif'
+}
+export interface OutputOptions {
+  /** Defaults to `Png`. */
+  format?: OutputFormat
+  /** 1-100, used by `Jpeg`, `Webp` and `Avif`. `Webp` is lossless when omitted. */
+  quality?: number
+  /** 0-9, used by `Png`. */
+  compressionLevel?: number
+  /** Used by `Jpeg`. */
+  progressive?: boolean
+  /** Embed an sRGB ICC profile. `Avif` is always tagged as sRGB instead. Defaults to `false`. */
+  iccProfile?: boolean
+}
+/** Which pixels close to the key color are removed. */
+export const enum BackgroundRemovalMode {
+  /**
+   * Only pixels connected to the image border, so matching colors inside
+   * the subject are kept.
+   */
+  Flood = 'Flood',
+  /** Every matching pixel. */
+  Global = 'Global'
+}
+export const enum ResizeModeType {
+  Width = 'Width',
+  Height = 'Height',
+  Scale = 'Scale',
+  /** Largest size that fits inside `width` x `height`. */
+  Fit = 'Fit',
+  /** Smallest size that covers `width` x `height`, cropped to it. */
+  Cover = 'Cover',
+  /** Exactly `width` x `height`, ignoring the aspect ratio. */
+  Exact = 'Exact',
+  /** Like `Fit`, but never enlarges the image. */
+  Max = 'Max'
+}
+export const enum ResizeFilter {
+  Nearest = 'Nearest',
+  Triangle = 'Triangle',
+  CatmullRom = 'CatmullRom',
+  Gaussian = 'Gaussian',
+  Lanczos3 = 'Lanczos3',
+  /** `Lanczos3`, falling back to cheaper filters for large downscales. */
+  Auto = 'Auto'
+}
+export interface ResizeMode {
+  type: ResizeModeType
+  /** Pixels for `Width` and `Height`, a factor for `Scale`. */
+  value?: number
+  /** Box width for `Fit`, `Cover`, `Exact` and `Max`. */
+  width?: number
+  /** Box height for `Fit`, `Cover`, `Exact` and `Max`. */
+  height?: number
+  /** Defaults to `Lanczos3`. */
+  filter?: ResizeFilter
+}
+export const enum OffsetModeType {
+  Pixel = 'Pixel',
+  Percent = 'Percent',
+  Center = 'Center'
+}
+/** Point of the layer that an offset refers to. */
+export const enum Anchor {
+  TopLeft = 'TopLeft',
+  Center = 'Center',
+  BottomRight = 'BottomRight'
+}
+export interface OffsetMode {
+  type: OffsetModeType
+  /** `[x, y]` of the layer anchor, required for `Pixel` and `Percent`. */
+  value?: Array<number>
+  /** Defaults to `Center`. Ignored by the `Center` type. */
+  anchor?: Anchor
+  /** Keep the layer fully inside the canvas. Defaults to `false`. */
+  clamp?: boolean
+}
+export const enum BackgroundType {
+  Solid = 'Solid',
+  LinearGradient = 'LinearGradient',
+  RadialGradient = 'RadialGradient',
+  Image = 'Image'
+}
+/** How a background image is scaled to the canvas. */
+export const enum BackgroundFit {
+  /** Fills the canvas, cropping what overflows. */
+  Cover = 'Cover',
+  /** Fits inside the canvas, centered on `color`. */
+  Contain = 'Contain'
+}
+export interface GradientStop {
+  /** 0-1 along the gradient. */
+  offset: number
+  color: string | Array<number>
+}
+export interface Background {
+  type: BackgroundType
+  /**
+   * Required for `Solid`. Behind the image for `Image`, defaults to
+   * transparent there.
+   */
+  color?: string | Array<number>
+  /** Required for the gradients, sorted by offset. */
+  stops?: Array<GradientStop>
+  /**
+   * `LinearGradient` direction in degrees, clockwise from "to top" as in
+   * CSS. Defaults to 180, top to bottom.
+   */
+  angle?: number
+  /** Encoded image, required for `Image`. */
+  buffer?: Buffer
+  /** Defaults to `Cover`. */
+  fit?: BackgroundFit
+}
+export interface CanvasOptions {
+  width: number
+  height: number
+  /** Used when `background` is not set. */
+  backgroundColor?: string | Array<number>
+  /**
+   * Defaults to the top-level `background` or `backgroundColor` in
+   * `buildCompositedImage` and to transparent in `composeLayers`.
+   */
+  background?: Background
+}
+export interface LayerOptions {
+  buffer: Buffer
+  resizeMode?: ResizeMode
+  /** Defaults to centering the layer on the canvas. */
+  offsetMode?: OffsetMode
+  /** 0-1, defaults to 1. */
+  opacity?: number
+  /** Defaults to `Normal`. */
+  blendMode?: BlendMode
+  /** Rotate or flip the image by its EXIF orientation. Defaults to `true`. */
+  autoOrient?: boolean
+}
+export interface LimitsOptions {
+  /** Largest canvas or resized layer, in pixels. Defaults to 100 megapixels. */
+  maxOutputPixels?: number
+  /** Largest input image width accepted by the decoder. */
+  maxWidth?: number
+  /** Largest input image height accepted by the decoder. */
+  maxHeight?: number
+  /** Most memory the decoder may allocate per image, in bytes. Defaults to 512 MiB. */
+  maxAllocBytes?: number
+}
+export interface ComposeLayersOptions {
+  /**
+   * Resize and blend in linear light instead of on gamma-encoded sRGB
+   * values. Slower, but downscaled edges don't darken. Defaults to `false`.
+   */
+  linearLight?: boolean
+  limits?: LimitsOptions
+  output?: OutputOptions
+  outputPath?: string
+}
+export interface RemoveBackgroundOptions {
+  /** Key color, alpha is ignored. Defaults to white. */
+  color?: string | Array<number>
+  /** 0-255, the largest per-channel difference that is fully removed. Defaults to 16. */
+  tolerance?: number
+  /** 0-255, how far past `tolerance` the alpha fades back in. Defaults to 16. */
+  feather?: number
+  /** Defaults to `Flood`. */
+  mode?: BackgroundRemovalMode
+}
+export interface AutoTrimOptions {
+  /**
+   * 0-255, how far a pixel may be from the border color, or from fully
+   * transparent, and still be trimmed. Defaults to 8.
+   */
+  tolerance?: number
+}
+export interface ShadowOptions {
+  /** Pixels, defaults to 0. */
+  offsetX?: number
+  /** Pixels, defaults to 0. */
+  offsetY?: number
+  /** 0-250 pixels, defaults to 10. */
+  blurRadius?: number
+  /** Defaults to black. */
+  color?: string | Array<number>
+  /** 0-1, defaults to 0.5. */
+  opacity?: number
+}
+export interface ProductOptions {
+  /** 0-1, defaults to 1. */
+  opacity?: number
+  /** Defaults to `Normal`. */
+  blendMode?: BlendMode
+  /** Makes the product's background transparent before compositing. */
+  removeBackground?: RemoveBackgroundOptions
+  /** Crops uniform or transparent margins before resizing and placement. */
+  autoTrim?: AutoTrimOptions
+  /** Drop shadow drawn beneath the product. */
+  shadow?: ShadowOptions
+  /**
+   * Degrees clockwise, applied after resizing. The product grows to its
+   * rotated bounds, which are used for positioning. Defaults to 0.
+   */
+  rotate?: number
+  /** Mirror the product horizontally, before rotating. Defaults to `false`. */
+  flipH?: boolean
+  /** Mirror the product vertically, before rotating. Defaults to `false`. */
+  flipV?: boolean
+}
+export interface OverlayOptions {
+  /** 0-1, defaults to 1. */
+  opacity?: number
+  /** Defaults to `Normal`. */
+  blendMode?: BlendMode
+}
 export interface BuildCompositedImageOptions {
-  backgroundColor: Array<number>
-  resizeMode?: object
-  offsetMode?: object
+  /** Used when `background` is not set. Defaults to transparent. */
+  backgroundColor?: string | Array<number>
+  background?: Background
+  /** Defaults to the overlay dimensions. */
+  canvas?: CanvasOptions
+  resizeMode?: ResizeMode
+  offsetMode?: OffsetMode
+  /** Defaults to centering the overlay on the canvas. */
+  overlayOffsetMode?: OffsetMode
+  product?: ProductOptions
+  overlay?: OverlayOptions
+  /**
+   * Rotate or flip the product and overlay by their EXIF orientation.
+   * Defaults to `true`.
+   */
+  autoOrient?: boolean
+  /**
+   * Resize and blend in linear light instead of on gamma-encoded sRGB
+   * values. Slower, but downscaled edges don't darken. Defaults to `false`.
+   */
+  linearLight?: boolean
+  limits?: LimitsOptions
+  output?: OutputOptions
+  outputPath?: string
+}
+/** Region of the decoded image kept by `autoTrim`, in source pixels. */
+export interface TrimBox {
+  x: number
+  y: number
+  width: number
+  height: number
 }
 export function sum(a: number, b: number): number
-export function buildCompositedImage(productBuffer: Buffer, overlayBuffer: Buffer, options: BuildCompositedImageOptions): void
+export function buildCompositedImage(productBuffer: Buffer, overlayBuffer: Buffer, options: BuildCompositedImageOptions): Buffer
+export function buildCompositedImageAsync(productBuffer: Buffer, overlayBuffer: Buffer, options: BuildCompositedImageOptions, signal?: AbortSignal | undefined | null): Promise<Buffer>
+export interface CompositeInfo {
+  buffer: Buffer
+  /** Set when `product.autoTrim` is used. */
+  productTrim?: TrimBox
+}
+/** `buildCompositedImage`, also reporting what was computed along the way. */
+export function buildCompositedImageWithInfo(productBuffer: Buffer, overlayBuffer: Buffer, options: BuildCompositedImageOptions): CompositeInfo
+export function buildCompositedImageWithInfoAsync(productBuffer: Buffer, overlayBuffer: Buffer, options: BuildCompositedImageOptions, signal?: AbortSignal | undefined | null): Promise<CompositeInfo>
+export function composeLayers(canvas: CanvasOptions, layers: Array<LayerOptions>, options?: ComposeLayersOptions | undefined | null): Buffer
+export function composeLayersAsync(canvas: CanvasOptions, layers: Array<LayerOptions>, options?: ComposeLayersOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<Buffer>
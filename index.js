@@ -252,7 +252,21 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { sum, buildCompositedImage } = nativeBinding
+const { sum, buildCompositedImage, buildCompositedImageAsync, buildCompositedImageWithInfo, buildCompositedImageWithInfoAsync, composeLayers, composeLayersAsync, BlendMode, OutputFormat, BackgroundRemovalMode, ResizeModeType, ResizeFilter, OffsetModeType, Anchor, BackgroundType, BackgroundFit } = nativeBinding
 
 module.exports.sum = sum
 module.exports.buildCompositedImage = buildCompositedImage
+module.exports.buildCompositedImageAsync = buildCompositedImageAsync
+module.exports.buildCompositedImageWithInfo = buildCompositedImageWithInfo
+module.exports.buildCompositedImageWithInfoAsync = buildCompositedImageWithInfoAsync
+module.exports.composeLayers = composeLayers
+module.exports.composeLayersAsync = composeLayersAsync
+module.exports.BlendMode = BlendMode
+module.exports.OutputFormat = OutputFormat
+module.exports.BackgroundRemovalMode = BackgroundRemovalMode
+module.exports.ResizeModeType = ResizeModeType
+module.exports.ResizeFilter = ResizeFilter
+module.exports.OffsetModeType = OffsetModeType
+module.exports.Anchor = Anchor
+module.exports.BackgroundType = BackgroundType
+module.exports.BackgroundFit = BackgroundFit
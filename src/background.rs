@@ -0,0 +1,137 @@
+use image::{Rgba, RgbaImage};
+use napi::bindgen_prelude::Buffer;
+use napi::Result;
+
+use crate::{
+  blend, decode_image, resize_image, BackgroundFit, BlendMode, ErrorCode, Limits, Resize,
+  ResizeFilter, ResizeMode,
+};
+
+#[derive(Clone, Copy)]
+pub struct GradientStop {
+  /// 0-1 along the gradient.
+  pub offset: f32,
+  pub color: Rgba<u8>,
+}
+
+/// What the layers are drawn on.
+pub enum Background {
+  Solid(Rgba<u8>),
+  /// `angle` is in degrees, clockwise from "to top" as in CSS.
+  LinearGradient {
+    stops: Vec<GradientStop>,
+    angle: f32,
+  },
+  /// Centered on the canvas and reaching its corners.
+  RadialGradient {
+    stops: Vec<GradientStop>,
+  },
+  /// `color` shows through transparent parts and the bars left by `Contain`.
+  Image {
+    buffer: Buffer,
+    fit: BackgroundFit,
+    color: Rgba<u8>,
+  },
+}
+
+impl Background {
+  /// `linear_light` applies to the background image, gradients are always
+  /// interpolated in sRGB as in CSS.
+  pub fn render(
+    &self,
+    width: u32,
+    height: u32,
+    limits: &Limits,
+    linear_light: bool,
+  ) -> Result<RgbaImage, ErrorCode> {
+    let center = (width as f32 / 2.0, height as f32 / 2.0);
+    // Offset of the pixel center from the canvas center.
+    let delta = |x: u32, y: u32| (x as f32 + 0.5 - center.0, y as f32 + 0.5 - center.1);
+
+    let canvas = match self {
+      Background::Solid(color) => RgbaImage::from_pixel(width, height, *color),
+      Background::LinearGradient { stops, angle } => {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        // The gradient line is long enough for the corners to get the end
+        // colors, as in CSS.
+        let length = (width as f32 * sin).abs() + (height as f32 * cos).abs();
+        RgbaImage::from_fn(width, height, |x, y| {
+          let (dx, dy) = delta(x, y);
+          gradient_color(stops, (dx * sin - dy * cos) / length + 0.5)
+        })
+      }
+      Background::RadialGradient { stops } => {
+        let radius = center.0.hypot(center.1);
+        RgbaImage::from_fn(width, height, |x, y| {
+          let (dx, dy) = delta(x, y);
+          gradient_color(stops, dx.hypot(dy) / radius)
+        })
+      }
+      Background::Image { buffer, fit, color } => {
+        let name = "background image";
+        let image = decode_image(buffer, name, ErrorCode::DecodeBackgroundFailed, limits)?;
+        let mode = match fit {
+          BackgroundFit::Cover => ResizeMode::Cover(width, height),
+          BackgroundFit::Contain => ResizeMode::Fit(width, height),
+        };
+        let resize = Resize {
+          mode,
+          filter: ResizeFilter::Auto,
+        };
+        let image =
+          resize_image(&image, resize, name, limits.max_output_pixels, linear_light)?.into_rgba8();
+
+        let mut canvas = RgbaImage::from_pixel(width, height, *color);
+        let x = (width.saturating_sub(image.width()) / 2) as i64;
+        let y = (height.saturating_sub(image.height()) / 2) as i64;
+        blend(
+          &mut canvas,
+          &image,
+          x,
+          y,
+          1.0,
+          BlendMode::Normal,
+          linear_light,
+        );
+        canvas
+      }
+    };
+    Ok(canvas)
+  }
+}
+
+// `stops` are sorted by offset and not empty.
+fn gradient_color(stops: &[GradientStop], position: f32) -> Rgba<u8> {
+  let position = position.clamp(0.0, 1.0);
+  let next = match stops.iter().position(|stop| stop.offset >= position) {
+    Some(0) => return stops[0].color,
+    Some(next) => next,
+    None => return stops[stops.len() - 1].color,
+  };
+
+  let (from, to) = (stops[next - 1], stops[next]);
+  let span = to.offset - from.offset;
+  if span <= 0.0 {
+    return to.color;
+  }
+  mix(from.color, to.color, (position - from.offset) / span)
+}
+
+// Interpolates with premultiplied alpha, so fading to transparent doesn't
+// pick up the transparent stop's color.
+fn mix(from: Rgba<u8>, to: Rgba<u8>, amount: f32) -> Rgba<u8> {
+  let from_alpha = from[3] as f32 * (1.0 - amount);
+  let to_alpha = to[3] as f32 * amount;
+  let alpha = from_alpha + to_alpha;
+  if alpha <= 0.0 {
+    return Rgba([0, 0, 0, 0]);
+  }
+
+  let mut out = [0u8; 4];
+  for channel in 0..3 {
+    let premultiplied = from[channel] as f32 * from_alpha + to[channel] as f32 * to_alpha;
+    out[channel] = (premultiplied / alpha).round().clamp(0.0, 255.0) as u8;
+  }
+  out[3] = alpha.round() as u8;
+  Rgba(out)
+}
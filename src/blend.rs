@@ -0,0 +1,132 @@
+use image::{Rgba, RgbaImage};
+
+use crate::linear::{from_linear, to_linear};
+
+#[napi(string_enum)]
+pub enum BlendMode {
+  Normal,
+  Multiply,
+  Screen,
+  Overlay,
+  SoftLight,
+  Darken,
+  Lighten,
+  Difference,
+  Additive,
+}
+
+/// Composites `layer` onto `canvas` with its top-left corner at `(x, y)`,
+/// scaling the layer's alpha by `opacity`. With `linear_light` the colors are
+/// mixed in linear light instead of as gamma-encoded sRGB values.
+pub fn blend(
+  canvas: &mut RgbaImage,
+  layer: &RgbaImage,
+  x: i64,
+  y: i64,
+  opacity: f32,
+  mode: BlendMode,
+  linear_light: bool,
+) {
+  let (canvas_width, canvas_height) = canvas.dimensions();
+  let (layer_width, layer_height) = layer.dimensions();
+
+  let x_start = x.max(0);
+  let y_start = y.max(0);
+  let x_end = x
+    .saturating_add(layer_width as i64)
+    .min(canvas_width as i64);
+  let y_end = y
+    .saturating_add(layer_height as i64)
+    .min(canvas_height as i64);
+
+  for canvas_y in y_start..y_end {
+    for canvas_x in x_start..x_end {
+      let source = layer.get_pixel((canvas_x - x) as u32, (canvas_y - y) as u32);
+      let backdrop = canvas.get_pixel_mut(canvas_x as u32, canvas_y as u32);
+      *backdrop = blend_pixel(*backdrop, *source, opacity, mode, linear_light);
+    }
+  }
+}
+
+// Separable blending followed by source-over, as in the W3C compositing spec.
+fn blend_pixel(
+  backdrop: Rgba<u8>,
+  source: Rgba<u8>,
+  opacity: f32,
+  mode: BlendMode,
+  linear_light: bool,
+) -> Rgba<u8> {
+  let source_alpha = source[3] as f32 / 255.0 * opacity;
+  if source_alpha <= 0.0 {
+    return backdrop;
+  }
+  let backdrop_alpha = backdrop[3] as f32 / 255.0;
+  let out_alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+
+  let decode = |value: u8| {
+    if linear_light {
+      to_linear(value)
+    } else {
+      value as f32 / 255.0
+    }
+  };
+
+  let mut out = [0u8; 4];
+  for channel in 0..3 {
+    let source_color = decode(source[channel]);
+    let backdrop_color = decode(backdrop[channel]);
+
+    let mixed = (1.0 - backdrop_alpha) * source_color
+      + backdrop_alpha * blend_channel(mode, backdrop_color, source_color);
+    let premultiplied =
+      source_alpha * mixed + backdrop_alpha * (1.0 - source_alpha) * backdrop_color;
+    let color = premultiplied / out_alpha;
+    out[channel] = if linear_light {
+      from_linear(color)
+    } else {
+      (color * 255.0).round() as u8
+    };
+  }
+  out[3] = (out_alpha * 255.0).round() as u8;
+  Rgba(out)
+}
+
+fn blend_channel(mode: BlendMode, backdrop: f32, source: f32) -> f32 {
+  match mode {
+    BlendMode::Normal => source,
+    BlendMode::Multiply => backdrop * source,
+    BlendMode::Screen => screen(backdrop, source),
+    BlendMode::Overlay => hard_light(source, backdrop),
+    BlendMode::SoftLight => soft_light(backdrop, source),
+    BlendMode::Darken => backdrop.min(source),
+    BlendMode::Lighten => backdrop.max(source),
+    BlendMode::Difference => (backdrop - source).abs(),
+    BlendMode::Additive => (backdrop + source).min(1.0),
+  }
+}
+
+fn screen(backdrop: f32, source: f32) -> f32 {
+  backdrop + source - backdrop * source
+}
+
+// Overlay is hard light with the layers swapped.
+fn hard_light(backdrop: f32, source: f32) -> f32 {
+  if source <= 0.5 {
+    backdrop * 2.0 * source
+  } else {
+    screen(backdrop, 2.0 * source - 1.0)
+  }
+}
+
+fn soft_light(backdrop: f32, source: f32) -> f32 {
+  if source <= 0.5 {
+    backdrop - (1.0 - 2.0 * source) * backdrop * (1.0 - backdrop)
+  } else {
+    let d = if backdrop <= 0.25 {
+      ((16.0 * backdrop - 12.0) * backdrop + 4.0) * backdrop
+    } else {
+      backdrop.sqrt()
+    };
+    backdrop + (2.0 * source - 1.0) * (d - backdrop)
+  }
+}
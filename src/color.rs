@@ -0,0 +1,337 @@
+use image::Rgba;
+use napi::Either;
+
+/// A color as accepted from JS: an `[r, g, b]` or `[r, g, b, a]` array, or a
+/// CSS color string. Array entries are taken as numbers so out-of-range ones
+/// are reported here rather than by napi's type conversion.
+pub type ColorInput = Either<String, Vec<f64>>;
+
+/// Parses every color format the options accept. The error reads as the
+/// rest of a sentence starting with the field name.
+pub fn parse_color_input(color: &ColorInput) -> Result<Rgba<u8>, String> {
+  match color {
+    Either::A(css) => {
+      parse_css_color(css).map_err(|reason| format!("is not a valid color \"{}\": {}", css, reason))
+    }
+    Either::B(channels) => {
+      if channels.len() != 3 && channels.len() != 4 {
+        return Err(format!(
+          "must have 3 or 4 entries (RGB or RGBA), got {}",
+          channels.len()
+        ));
+      }
+      let mut rgba = [255u8; 4];
+      for (channel, &value) in rgba.iter_mut().zip(channels) {
+        *channel = parse_channel(value)?;
+      }
+      Ok(Rgba(rgba))
+    }
+  }
+}
+
+fn parse_channel(value: f64) -> Result<u8, String> {
+  if value.fract() != 0.0 || !(0.0..=255.0).contains(&value) {
+    return Err(format!(
+      "entries must be integers between 0 and 255, got {}",
+      value
+    ));
+  }
+  Ok(value as u8)
+}
+
+fn parse_css_color(css: &str) -> Result<Rgba<u8>, String> {
+  let color = css.trim().to_ascii_lowercase();
+
+  if let Some(hex) = color.strip_prefix('#') {
+    return parse_hex(hex).ok_or_else(|| "expected #RGB, #RGBA, #RRGGBB or #RRGGBBAA".to_string());
+  }
+
+  if let Some((function, args)) = color
+    .strip_suffix(')')
+    .and_then(|color| color.split_once('('))
+  {
+    let args: Vec<&str> = args
+      .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+      .filter(|arg| !arg.is_empty())
+      .collect();
+    if args.len() != 3 && args.len() != 4 {
+      return Err(format!("expected 3 or 4 arguments, got {}", args.len()));
+    }
+    let alpha = match args.get(3) {
+      Some(alpha) => parse_alpha(alpha)?,
+      None => 255,
+    };
+    let [red, green, blue] = match function.trim() {
+      "rgb" | "rgba" => [
+        parse_rgb_channel(args[0])?,
+        parse_rgb_channel(args[1])?,
+        parse_rgb_channel(args[2])?,
+      ],
+      "hsl" | "hsla" => hsl_to_rgb(
+        parse_hue(args[0])?,
+        parse_percentage(args[1])?,
+        parse_percentage(args[2])?,
+      ),
+      _ => {
+        return Err(format!(
+          "expected rgb(), rgba(), hsl() or hsla(), got {}()",
+          function.trim()
+        ))
+      }
+    };
+    return Ok(Rgba([red, green, blue, alpha]));
+  }
+
+  if color == "transparent" {
+    return Ok(Rgba([0, 0, 0, 0]));
+  }
+  named_color(&color)
+    .map(|[red, green, blue]| Rgba([red, green, blue, 255]))
+    .ok_or_else(|| "unknown color name".to_string())
+}
+
+fn parse_hex(hex: &str) -> Option<Rgba<u8>> {
+  if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+    return None;
+  }
+  let digit = |index: usize| u8::from_str_radix(&hex[index..index + 1], 16).ok();
+  let pair = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+
+  match hex.len() {
+    3 | 4 => {
+      let mut channels = [255; 4];
+      for (index, channel) in channels.iter_mut().enumerate().take(hex.len()) {
+        *channel = digit(index)? * 17;
+      }
+      Some(Rgba(channels))
+    }
+    6 | 8 => {
+      let mut channels = [255; 4];
+      for (index, channel) in channels.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = pair(index * 2)?;
+      }
+      Some(Rgba(channels))
+    }
+    _ => None,
+  }
+}
+
+// A number 0-255 or a percentage.
+fn parse_rgb_channel(arg: &str) -> Result<u8, String> {
+  let value = match arg.strip_suffix('%') {
+    Some(percent) => parse_number(percent, arg)? / 100.0 * 255.0,
+    None => parse_number(arg, arg)?,
+  };
+  if !(0.0..=255.0).contains(&value) {
+    return Err(format!("\"{}\" is outside 0-255 or 0%-100%", arg));
+  }
+  Ok(value.round() as u8)
+}
+
+// A number 0-1 or a percentage.
+fn parse_alpha(arg: &str) -> Result<u8, String> {
+  let value = match arg.strip_suffix('%') {
+    Some(percent) => parse_number(percent, arg)? / 100.0,
+    None => parse_number(arg, arg)?,
+  };
+  if !(0.0..=1.0).contains(&value) {
+    return Err(format!("alpha \"{}\" is outside 0-1 or 0%-100%", arg));
+  }
+  Ok((value * 255.0).round() as u8)
+}
+
+// Degrees, with or without the `deg` unit.
+fn parse_hue(arg: &str) -> Result<f64, String> {
+  let degrees = parse_number(arg.strip_suffix("deg").unwrap_or(arg), arg)?;
+  Ok(degrees.rem_euclid(360.0))
+}
+
+// 0-100, with or without the `%` sign, as a 0-1 fraction.
+fn parse_percentage(arg: &str) -> Result<f64, String> {
+  let value = parse_number(arg.strip_suffix('%').unwrap_or(arg), arg)?;
+  if !(0.0..=100.0).contains(&value) {
+    return Err(format!("\"{}\" is outside 0%-100%", arg));
+  }
+  Ok(value / 100.0)
+}
+
+fn parse_number(number: &str, arg: &str) -> Result<f64, String> {
+  number
+    .parse::<f64>()
+    .ok()
+    .filter(|number| number.is_finite())
+    .ok_or_else(|| format!("\"{}\" is not a number", arg))
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [u8; 3] {
+  let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+  let sector = hue / 60.0;
+  let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+  let (red, green, blue) = match sector as u32 {
+    0 => (chroma, x, 0.0),
+    1 => (x, chroma, 0.0),
+    2 => (0.0, chroma, x),
+    3 => (0.0, x, chroma),
+    4 => (x, 0.0, chroma),
+    _ => (chroma, 0.0, x),
+  };
+  let lightness_match = lightness - chroma / 2.0;
+  let channel = |value: f64| ((value + lightness_match) * 255.0).round() as u8;
+  [channel(red), channel(green), channel(blue)]
+}
+
+// The CSS named colors.
+fn named_color(name: &str) -> Option<[u8; 3]> {
+  let rgb = match name {
+    "aliceblue" => [240, 248, 255],
+    "antiquewhite" => [250, 235, 215],
+    "aqua" => [0, 255, 255],
+    "aquamarine" => [127, 255, 212],
+    "azure" => [240, 255, 255],
+    "beige" => [245, 245, 220],
+    "bisque" => [255, 228, 196],
+    "black" => [0, 0, 0],
+    "blanchedalmond" => [255, 235, 205],
+    "blue" => [0, 0, 255],
+    "blueviolet" => [138, 43, 226],
+    "brown" => [165, 42, 42],
+    "burlywood" => [222, 184, 135],
+    "cadetblue" => [95, 158, 160],
+    "chartreuse" => [127, 255, 0],
+    "chocolate" => [210, 105, 30],
+    "coral" => [255, 127, 80],
+    "cornflowerblue" => [100, 149, 237],
+    "cornsilk" => [255, 248, 220],
+    "crimson" => [220, 20, 60],
+    "cyan" => [0, 255, 255],
+    "darkblue" => [0, 0, 139],
+    "darkcyan" => [0, 139, 139],
+    "darkgoldenrod" => [184, 134, 11],
+    "darkgray" => [169, 169, 169],
+    "darkgreen" => [0, 100, 0],
+    "darkgrey" => [169, 169, 169],
+    "darkkhaki" => [189, 183, 107],
+    "darkmagenta" => [139, 0, 139],
+    "darkolivegreen" => [85, 107, 47],
+    "darkorange" => [255, 140, 0],
+    "darkorchid" => [153, 50, 204],
+    "darkred" => [139, 0, 0],
+    "darksalmon" => [233, 150, 122],
+    "darkseagreen" => [143, 188, 143],
+    "darkslateblue" => [72, 61, 139],
+    "darkslategray" => [47, 79, 79],
+    "darkslategrey" => [47, 79, 79],
+    "darkturquoise" => [0, 206, 209],
+    "darkviolet" => [148, 0, 211],
+    "deeppink" => [255, 20, 147],
+    "deepskyblue" => [0, 191, 255],
+    "dimgray" => [105, 105, 105],
+    "dimgrey" => [105, 105, 105],
+    "dodgerblue" => [30, 144, 255],
+    "firebrick" => [178, 34, 34],
+    "floralwhite" => [255, 250, 240],
+    "forestgreen" => [34, 139, 34],
+    "fuchsia" => [255, 0, 255],
+    "gainsboro" => [220, 220, 220],
+    "ghostwhite" => [248, 248, 255],
+    "gold" => [255, 215, 0],
+    "goldenrod" => [218, 165, 32],
+    "gray" => [128, 128, 128],
+    "green" => [0, 128, 0],
+    "greenyellow" => [173, 255, 47],
+    "grey" => [128, 128, 128],
+    "honeydew" => [240, 255, 240],
+    "hotpink" => [255, 105, 180],
+    "indianred" => [205, 92, 92],
+    "indigo" => [75, 0, 130],
+    "ivory" => [255, 255, 240],
+    "khaki" => [240, 230, 140],
+    "lavender" => [230, 230, 250],
+    "lavenderblush" => [255, 240, 245],
+    "lawngreen" => [124, 252, 0],
+    "lemonchiffon" => [255, 250, 205],
+    "lightblue" => [173, 216, 230],
+    "lightcoral" => [240, 128, 128],
+    "lightcyan" => [224, 255, 255],
+    "lightgoldenrodyellow" => [250, 250, 210],
+    "lightgray" => [211, 211, 211],
+    "lightgreen" => [144, 238, 144],
+    "lightgrey" => [211, 211, 211],
+    "lightpink" => [255, 182, 193],
+    "lightsalmon" => [255, 160, 122],
+    "lightseagreen" => [32, 178, 170],
+    "lightskyblue" => [135, 206, 250],
+    "lightslategray" => [119, 136, 153],
+    "lightslategrey" => [119, 136, 153],
+    "lightsteelblue" => [176, 196, 222],
+    "lightyellow" => [255, 255, 224],
+    "lime" => [0, 255, 0],
+    "limegreen" => [50, 205, 50],
+    "linen" => [250, 240, 230],
+    "magenta" => [255, 0, 255],
+    "maroon" => [128, 0, 0],
+    "mediumaquamarine" => [102, 205, 170],
+    "mediumblue" => [0, 0, 205],
+    "mediumorchid" => [186, 85, 211],
+    "mediumpurple" => [147, 112, 219],
+    "mediumseagreen" => [60, 179, 113],
+    "mediumslateblue" => [123, 104, 238],
+    "mediumspringgreen" => [0, 250, 154],
+    "mediumturquoise" => [72, 209, 204],
+    "mediumvioletred" => [199, 21, 133],
+    "midnightblue" => [25, 25, 112],
+    "mintcream" => [245, 255, 250],
+    "mistyrose" => [255, 228, 225],
+    "moccasin" => [255, 228, 181],
+    "navajowhite" => [255, 222, 173],
+    "navy" => [0, 0, 128],
+    "oldlace" => [253, 245, 230],
+    "olive" => [128, 128, 0],
+    "olivedrab" => [107, 142, 35],
+    "orange" => [255, 165, 0],
+    "orangered" => [255, 69, 0],
+    "orchid" => [218, 112, 214],
+    "palegoldenrod" => [238, 232, 170],
+    "palegreen" => [152, 251, 152],
+    "paleturquoise" => [175, 238, 238],
+    "palevioletred" => [219, 112, 147],
+    "papayawhip" => [255, 239, 213],
+    "peachpuff" => [255, 218, 185],
+    "peru" => [205, 133, 63],
+    "pink" => [255, 192, 203],
+    "plum" => [221, 160, 221],
+    "powderblue" => [176, 224, 230],
+    "purple" => [128, 0, 128],
+    "rebeccapurple" => [102, 51, 153],
+    "red" => [255, 0, 0],
+    "rosybrown" => [188, 143, 143],
+    "royalblue" => [65, 105, 225],
+    "saddlebrown" => [139, 69, 19],
+    "salmon" => [250, 128, 114],
+    "sandybrown" => [244, 164, 96],
+    "seagreen" => [46, 139, 87],
+    "seashell" => [255, 245, 238],
+    "sienna" => [160, 82, 45],
+    "silver" => [192, 192, 192],
+    "skyblue" => [135, 206, 235],
+    "slateblue" => [106, 90, 205],
+    "slategray" => [112, 128, 144],
+    "slategrey" => [112, 128, 144],
+    "snow" => [255, 250, 250],
+    "springgreen" => [0, 255, 127],
+    "steelblue" => [70, 130, 180],
+    "tan" => [210, 180, 140],
+    "teal" => [0, 128, 128],
+    "thistle" => [216, 191, 216],
+    "tomato" => [255, 99, 71],
+    "turquoise" => [64, 224, 208],
+    "violet" => [238, 130, 238],
+    "wheat" => [245, 222, 179],
+    "white" => [255, 255, 255],
+    "whitesmoke" => [245, 245, 245],
+    "yellow" => [255, 255, 0],
+    "yellowgreen" => [154, 205, 50],
+    _ => return None,
+  };
+  Some(rgb)
+}
@@ -0,0 +1,306 @@
+use std::io::{Cursor, Write};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+use image::codecs::webp::{WebPEncoder, WebPQuality};
+use image::{ColorType, ImageEncoder, RgbaImage};
+use napi::{Error, Result};
+
+use crate::{icc, ErrorCode};
+
+const DEFAULT_QUALITY: u8 = 80;
+
+#[napi(string_enum)]
+pub enum OutputFormat {
+  Png,
+  Jpeg,
+  Webp,
+  Avif,
+}
+
+#[napi(object)]
+pub struct OutputOptions {
+  /// Defaults to `Png`.
+  pub format: Option<OutputFormat>,
+  /// 1-100, used by `Jpeg`, `Webp` and `Avif`. `Webp` is lossless when omitted.
+  pub quality: Option<u32>,
+  /// 0-9, used by `Png`.
+  pub compression_level: Option<u32>,
+  /// Used by `Jpeg`.
+  pub progressive: Option<bool>,
+  /// Embed an sRGB ICC profile. `Avif` is always tagged as sRGB instead.
+  /// Defaults to `false`.
+  pub icc_profile: Option<bool>,
+}
+
+/// Validated encoder settings resolved from [`OutputOptions`].
+#[derive(Clone, Copy)]
+pub struct Encoding {
+  codec: Codec,
+  srgb_profile: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Codec {
+  Png { compression: CompressionType },
+  Jpeg { quality: u8, progressive: bool },
+  Webp { quality: Option<u8> },
+  Avif { quality: u8 },
+}
+
+impl Encoding {
+  pub fn from_options(options: Option<&OutputOptions>) -> Result<Self, ErrorCode> {
+    let options = match options {
+      Some(options) => options,
+      None => {
+        return Ok(Encoding {
+          codec: Codec::Png {
+            compression: CompressionType::Default,
+          },
+          srgb_profile: false,
+        })
+      }
+    };
+
+    let quality = match options.quality {
+      Some(quality @ 1..=100) => Some(quality as u8),
+      Some(quality) => {
+        return Err(Error::new(
+          ErrorCode::InvalidOutput,
+          format!("output.quality must be between 1 and 100, got {}", quality),
+        ))
+      }
+      None => None,
+    };
+
+    let codec = match options.format.unwrap_or(OutputFormat::Png) {
+      OutputFormat::Png => {
+        let compression = match options.compression_level {
+          None => CompressionType::Default,
+          Some(0..=3) => CompressionType::Fast,
+          Some(4..=6) => CompressionType::Default,
+          Some(7..=9) => CompressionType::Best,
+          Some(level) => {
+            return Err(Error::new(
+              ErrorCode::InvalidOutput,
+              format!(
+                "output.compressionLevel must be between 0 and 9, got {}",
+                level
+              ),
+            ))
+          }
+        };
+        Codec::Png { compression }
+      }
+      OutputFormat::Jpeg => Codec::Jpeg {
+        quality: quality.unwrap_or(DEFAULT_QUALITY),
+        progressive: options.progressive.unwrap_or(false),
+      },
+      OutputFormat::Webp => Codec::Webp { quality },
+      OutputFormat::Avif => Codec::Avif {
+        quality: quality.unwrap_or(DEFAULT_QUALITY),
+      },
+    };
+    Ok(Encoding {
+      codec,
+      srgb_profile: options.icc_profile.unwrap_or(false),
+    })
+  }
+
+  pub fn encode(&self, image: &RgbaImage) -> Result<Vec<u8>, ErrorCode> {
+    self.encode_image(image).map_err(|reason| {
+      Error::new(
+        ErrorCode::EncodeFailed,
+        format!("Failed to encode image: {}", reason),
+      )
+    })
+  }
+
+  fn encode_image(&self, image: &RgbaImage) -> std::result::Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let mut bytes: Vec<u8> = Vec::new();
+    let profile = if self.srgb_profile {
+      Some(icc::srgb_profile()?)
+    } else {
+      None
+    };
+
+    match self.codec {
+      Codec::Png { compression } => {
+        PngEncoder::new_with_quality(
+          Cursor::new(&mut bytes),
+          compression,
+          PngFilterType::Adaptive,
+        )
+        .write_image(image.as_raw(), width, height, ColorType::Rgba8)
+        .map_err(|err| err.to_string())?;
+        if let Some(profile) = &profile {
+          bytes = embed_png_profile(&bytes, profile)?;
+        }
+      }
+      Codec::Jpeg {
+        quality,
+        progressive,
+      } => encode_jpeg(image, quality, progressive, profile.as_deref(), &mut bytes)?,
+      Codec::Webp { quality } => {
+        // Lossy WebP goes through image's libwebp binding, which is
+        // deprecated and slated for removal. This pins `image` to 0.24;
+        // upgrading means dropping lossy `quality` for `Webp`.
+        #[allow(deprecated)]
+        let encoder = match quality {
+          Some(quality) => {
+            WebPEncoder::new_with_quality(Cursor::new(&mut bytes), WebPQuality::lossy(quality))
+          }
+          None => WebPEncoder::new_lossless(Cursor::new(&mut bytes)),
+        };
+        encoder
+          .write_image(image.as_raw(), width, height, ColorType::Rgba8)
+          .map_err(|err| err.to_string())?;
+        if let Some(profile) = &profile {
+          bytes = embed_webp_profile(&bytes, profile, (width, height))?;
+        }
+      }
+      // ravif tags the file as sRGB through its `colr` box.
+      Codec::Avif { quality } => encode_avif(image, quality, &mut bytes)?,
+    }
+    Ok(bytes)
+  }
+}
+
+// The `image` JPEG encoder has no progressive mode, so JPEG goes through
+// `jpeg-encoder` instead.
+fn encode_jpeg(
+  image: &RgbaImage,
+  quality: u8,
+  progressive: bool,
+  profile: Option<&[u8]>,
+  bytes: &mut Vec<u8>,
+) -> std::result::Result<(), String> {
+  let (width, height) = image.dimensions();
+  let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+    (Ok(width), Ok(height)) => (width, height),
+    _ => return Err(format!("{}x{} exceeds the JPEG size limit", width, height)),
+  };
+
+  let mut encoder = jpeg_encoder::Encoder::new(bytes, quality);
+  encoder.set_progressive(progressive);
+  if let Some(profile) = profile {
+    encoder
+      .add_icc_profile(profile)
+      .map_err(|err| err.to_string())?;
+  }
+  encoder
+    .encode(image.as_raw(), width, height, jpeg_encoder::ColorType::Rgba)
+    .map_err(|err| err.to_string())
+}
+
+// `image`'s own AVIF support pulls in rav1e's assembly, which needs nasm at
+// build time, so ravif is used directly without it.
+fn encode_avif(
+  image: &RgbaImage,
+  quality: u8,
+  bytes: &mut Vec<u8>,
+) -> std::result::Result<(), String> {
+  let pixels: Vec<ravif::RGBA8> = image
+    .pixels()
+    .map(|pixel| ravif::RGBA8::new(pixel[0], pixel[1], pixel[2], pixel[3]))
+    .collect();
+  let (width, height) = image.dimensions();
+
+  let encoded = ravif::Encoder::new()
+    .with_quality(quality as f32)
+    .with_alpha_quality(quality as f32)
+    .encode_rgba(ravif::Img::new(
+      &pixels[..],
+      width as usize,
+      height as usize,
+    ))
+    .map_err(|err| err.to_string())?;
+  *bytes = encoded.avif_file;
+  Ok(())
+}
+
+// `image`'s PNG encoder can't write an `iCCP` chunk, so it is spliced in
+// right after `IHDR`, where the spec wants it.
+fn embed_png_profile(png: &[u8], profile: &[u8]) -> std::result::Result<Vec<u8>, String> {
+  // Signature, then the IHDR chunk: length, type, 13 bytes of data, CRC.
+  const IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+  if png.len() < IHDR_END || &png[12..16] != b"IHDR" {
+    return Err("unexpected PNG layout".to_string());
+  }
+
+  let mut compressed = ZlibEncoder::new(Vec::new(), Compression::default());
+  compressed
+    .write_all(profile)
+    .map_err(|err| err.to_string())?;
+  let compressed = compressed.finish().map_err(|err| err.to_string())?;
+
+  // Profile name, its terminator and the compression method (zlib).
+  let mut data = b"sRGB\0\0".to_vec();
+  data.extend_from_slice(&compressed);
+
+  let mut out = Vec::with_capacity(png.len() + data.len() + 12);
+  out.extend_from_slice(&png[..IHDR_END]);
+  out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+  let mut crc = crc32fast::Hasher::new();
+  crc.update(b"iCCP");
+  crc.update(&data);
+  out.extend_from_slice(b"iCCP");
+  out.extend_from_slice(&data);
+  out.extend_from_slice(&crc.finalize().to_be_bytes());
+  out.extend_from_slice(&png[IHDR_END..]);
+  Ok(out)
+}
+
+// Profiles need the extended WebP layout: a `VP8X` header with the ICC flag
+// set, then the `ICCP` chunk before the image data.
+fn embed_webp_profile(
+  webp: &[u8],
+  profile: &[u8],
+  (width, height): (u32, u32),
+) -> std::result::Result<Vec<u8>, String> {
+  const ICC_FLAG: u8 = 0x20;
+  const ALPHA_FLAG: u8 = 0x10;
+  if webp.len() < 20 || &webp[0..4] != b"RIFF" || &webp[8..12] != b"WEBP" {
+    return Err("unexpected WebP layout".to_string());
+  }
+  let chunks = &webp[12..];
+
+  let mut body = Vec::with_capacity(webp.len() + profile.len() + 32);
+  let rest = if &chunks[0..4] == b"VP8X" {
+    let mut vp8x = chunks[..18].to_vec();
+    vp8x[8] |= ICC_FLAG;
+    body.extend_from_slice(&vp8x);
+    &chunks[18..]
+  } else {
+    // Lossless output keeps its alpha inside the `VP8L` chunk.
+    let flags = if &chunks[0..4] == b"VP8L" {
+      ICC_FLAG | ALPHA_FLAG
+    } else {
+      ICC_FLAG
+    };
+    body.extend_from_slice(b"VP8X");
+    body.extend_from_slice(&10u32.to_le_bytes());
+    body.extend_from_slice(&[flags, 0, 0, 0]);
+    body.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+    body.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+    chunks
+  };
+
+  body.extend_from_slice(b"ICCP");
+  body.extend_from_slice(&(profile.len() as u32).to_le_bytes());
+  body.extend_from_slice(profile);
+  if profile.len() % 2 == 1 {
+    body.push(0);
+  }
+  body.extend_from_slice(rest);
+
+  let mut out = Vec::with_capacity(body.len() + 12);
+  out.extend_from_slice(b"RIFF");
+  out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+  out.extend_from_slice(b"WEBP");
+  out.extend_from_slice(&body);
+  Ok(out)
+}
@@ -0,0 +1,49 @@
+/// Error codes exposed to JS as `error.code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+  InvalidResizeMode,
+  InvalidOffsetMode,
+  InvalidColor,
+  InvalidCanvas,
+  InvalidOpacity,
+  InvalidRemoveBackground,
+  InvalidAutoTrim,
+  InvalidShadow,
+  InvalidBackground,
+  InvalidTransform,
+  InvalidOutput,
+  LimitExceeded,
+  InputTooLarge,
+  DecodeProductFailed,
+  DecodeOverlayFailed,
+  DecodeLayerFailed,
+  DecodeBackgroundFailed,
+  EncodeFailed,
+  WriteOutputFailed,
+}
+
+impl AsRef<str> for ErrorCode {
+  fn as_ref(&self) -> &str {
+    match self {
+      ErrorCode::InvalidResizeMode => "INVALID_RESIZE_MODE",
+      ErrorCode::InvalidOffsetMode => "INVALID_OFFSET_MODE",
+      ErrorCode::InvalidColor => "INVALID_COLOR",
+      ErrorCode::InvalidCanvas => "INVALID_CANVAS",
+      ErrorCode::InvalidOpacity => "INVALID_OPACITY",
+      ErrorCode::InvalidRemoveBackground => "INVALID_REMOVE_BACKGROUND",
+      ErrorCode::InvalidAutoTrim => "INVALID_AUTO_TRIM",
+      ErrorCode::InvalidShadow => "INVALID_SHADOW",
+      ErrorCode::InvalidBackground => "INVALID_BACKGROUND",
+      ErrorCode::InvalidTransform => "INVALID_TRANSFORM",
+      ErrorCode::InvalidOutput => "INVALID_OUTPUT",
+      ErrorCode::LimitExceeded => "LIMIT_EXCEEDED",
+      ErrorCode::InputTooLarge => "INPUT_TOO_LARGE",
+      ErrorCode::DecodeProductFailed => "DECODE_PRODUCT_FAILED",
+      ErrorCode::DecodeOverlayFailed => "DECODE_OVERLAY_FAILED",
+      ErrorCode::DecodeLayerFailed => "DECODE_LAYER_FAILED",
+      ErrorCode::DecodeBackgroundFailed => "DECODE_BACKGROUND_FAILED",
+      ErrorCode::EncodeFailed => "ENCODE_FAILED",
+      ErrorCode::WriteOutputFailed => "WRITE_OUTPUT_FAILED",
+    }
+  }
+}
@@ -0,0 +1,119 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::error::{DecodingError, ImageFormatHint};
+use image::{
+  DynamicImage, GrayAlphaImage, ImageDecoder, ImageError, ImageFormat, ImageResult, RgbImage,
+  RgbaImage,
+};
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+
+/// Decodes a CMYK JPEG with an embedded profile straight to sRGB.
+///
+/// `image` flattens CMYK to RGB without the profile, which loses the black
+/// channel's real contribution, so these are decoded from the raw samples.
+/// Returns `None` for every other image.
+pub fn decode_cmyk_jpeg(
+  buffer: &[u8],
+  limits: &image::io::Limits,
+) -> ImageResult<Option<DynamicImage>> {
+  let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(buffer));
+  decoder.read_info().map_err(jpeg_error)?;
+  let info = match decoder.info() {
+    Some(info) if info.pixel_format == jpeg_decoder::PixelFormat::CMYK32 => info,
+    _ => return Ok(None),
+  };
+  let profile = match decoder
+    .icc_profile()
+    .and_then(|icc| ColorProfile::new_from_slice(&icc).ok())
+  {
+    Some(profile) if profile.color_space == DataColorSpace::Cmyk => profile,
+    _ => return Ok(None),
+  };
+
+  let (width, height) = (info.width as u32, info.height as u32);
+  limits.check_dimensions(width, height)?;
+  limits.clone().reserve(width as u64 * height as u64 * 7)?;
+
+  let cmyk = decoder.decode().map_err(jpeg_error)?;
+  let transform = match profile.create_transform_8bit(
+    Layout::Rgba,
+    &ColorProfile::new_srgb(),
+    Layout::Rgb,
+    TransformOptions::default(),
+  ) {
+    Ok(transform) => transform,
+    Err(_) => return Ok(None),
+  };
+  let mut rgb = vec![0u8; width as usize * height as usize * 3];
+  if transform.transform(&cmyk, &mut rgb).is_err() {
+    return Ok(None);
+  }
+  Ok(RgbImage::from_raw(width, height, rgb).map(DynamicImage::ImageRgb8))
+}
+
+/// Converts an RGB or grayscale image with an embedded ICC profile to sRGB.
+///
+/// Images without a profile are assumed to already be sRGB. A profile that
+/// can't be read or applied is ignored, as `auto_orient` does with a broken
+/// EXIF block.
+pub fn convert_to_srgb(image: DynamicImage, buffer: &[u8], format: ImageFormat) -> DynamicImage {
+  let profile = match read_icc_profile(buffer, format)
+    .and_then(|icc| ColorProfile::new_from_slice(&icc).ok())
+  {
+    Some(profile) => profile,
+    None => return image,
+  };
+
+  // Gray stays gray, with the sRGB tone curve.
+  let (source, layout) = match profile.color_space {
+    DataColorSpace::Rgb => (image.to_rgba8().into_raw(), Layout::Rgba),
+    DataColorSpace::Gray => (image.to_luma_alpha8().into_raw(), Layout::GrayAlpha),
+    _ => return image,
+  };
+  let transform = match profile.create_transform_8bit(
+    layout,
+    &ColorProfile::new_srgb(),
+    layout,
+    TransformOptions::default(),
+  ) {
+    Ok(transform) => transform,
+    Err(_) => return image,
+  };
+
+  let (width, height) = (image.width(), image.height());
+  let mut converted = vec![0u8; source.len()];
+  if transform.transform(&source, &mut converted).is_err() {
+    return image;
+  }
+  let converted = match layout {
+    Layout::Rgba => RgbaImage::from_raw(width, height, converted).map(DynamicImage::ImageRgba8),
+    _ => GrayAlphaImage::from_raw(width, height, converted).map(DynamicImage::ImageLumaA8),
+  };
+  converted.unwrap_or(image)
+}
+
+/// The sRGB profile embedded in outputs.
+pub fn srgb_profile() -> Result<Vec<u8>, String> {
+  ColorProfile::new_srgb()
+    .encode()
+    .map_err(|err| format!("could not build the sRGB profile: {}", err))
+}
+
+// `image` only exposes profiles through format-specific decoders. Creating
+// one reads the headers, not the pixels.
+fn read_icc_profile(buffer: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+  match format {
+    ImageFormat::Jpeg => JpegDecoder::new(Cursor::new(buffer)).ok()?.icc_profile(),
+    ImageFormat::Png => PngDecoder::new(Cursor::new(buffer)).ok()?.icc_profile(),
+    _ => None,
+  }
+}
+
+fn jpeg_error(err: jpeg_decoder::Error) -> ImageError {
+  ImageError::Decoding(DecodingError::new(
+    ImageFormatHint::Exact(ImageFormat::Jpeg),
+    err,
+  ))
+}
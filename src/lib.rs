@@ -3,86 +3,83 @@
 #[macro_use]
 extern crate napi_derive;
 
-use image::imageops::{overlay, FilterType};
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
-use napi::{bindgen_prelude::*, Error, JsObject, Result, Status};
+use std::io::Cursor;
 
+use image::imageops::FilterType;
+use image::{
+  DynamicImage, GenericImageView, ImageError, ImageFormat, ImageResult, Rgba, RgbaImage,
+};
+use napi::{bindgen_prelude::*, Error, Result};
+
+mod background;
+mod blend;
+mod color;
+mod encode;
+mod error;
+mod icc;
+mod linear;
+mod matte;
+mod options;
+mod orientation;
+mod shadow;
+mod task;
+mod transform;
+mod trim;
+
+pub use background::{Background, GradientStop};
+pub use blend::{blend, BlendMode};
+pub use color::{parse_color_input, ColorInput};
+pub use encode::{Encoding, OutputFormat, OutputOptions};
+pub use error::ErrorCode;
+pub use matte::{remove_background, BackgroundRemoval, BackgroundRemovalMode};
+pub use options::{
+  get_limits, Anchor, AutoTrimOptions, BackgroundFit, BackgroundOptions, BackgroundType,
+  BuildCompositedImageOptions, CanvasOptions, ComposeLayersOptions, GradientStopOptions,
+  LayerOptions, LimitsOptions, OffsetModeOptions, OffsetModeType, OverlayOptions, ProductOptions,
+  RemoveBackgroundOptions, ResizeFilter, ResizeModeOptions, ResizeModeType, ShadowOptions,
+};
+pub use orientation::auto_orient;
+pub use shadow::{render_shadow, Shadow};
+pub use task::CompositeTask;
+pub use transform::{apply_transform, Transform};
+pub use trim::{find_trim_box, AutoTrim, TrimBox};
+
+#[derive(Clone, Copy)]
 pub enum ResizeMode {
   Width(u32),
   Height(u32),
   Scale(f32),
+  Fit(u32, u32),
+  Cover(u32, u32),
+  Exact(u32, u32),
+  Max(u32, u32),
 }
 
+#[derive(Clone, Copy)]
 pub enum OffsetMode {
   Pixel(i64, i64),
   Percent(f32, f32),
   Center,
 }
 
-#[napi(object)]
-pub struct BuildCompositedImageOptions {
-  pub background_color: Vec<u8>,
-  pub resize_mode: Option<JsObject>,
-  pub offset_mode: Option<JsObject>,
-}
-
-impl BuildCompositedImageOptions {
-  pub fn get_resize_mode(&self) -> Result<Option<ResizeMode>> {
-    if let Some(resize_mode_obj) = &self.resize_mode {
-      let type_str: String = resize_mode_obj.get_named_property::<String>("type")?;
-
-      let resize_mode = match type_str.as_str() {
-        "Width" => {
-          let value: u32 = resize_mode_obj.get_named_property::<u32>("value")?;
-          ResizeMode::Width(value)
-        }
-        "Height" => {
-          let value: u32 = resize_mode_obj.get_named_property::<u32>("value")?;
-          ResizeMode::Height(value)
-        }
-        "Scale" => {
-          let value: f64 = resize_mode_obj.get_named_property::<f64>("value")?;
-          ResizeMode::Scale(value as f32)
-        }
-        _ => {
-          return Err(Error::new(
-            Status::InvalidArg,
-            "Invalid ResizeMode type".to_string(),
-          ))
-        }
-      };
-      Ok(Some(resize_mode))
-    } else {
-      Ok(None)
-    }
-  }
-
-  pub fn get_offset_mode(&self) -> Result<OffsetMode> {
-    if let Some(offset_mode_obj) = &self.offset_mode {
-      let type_str: String = offset_mode_obj.get_named_property::<String>("type")?;
-
-      let offset_mode = match type_str.as_str() {
-        "Pixel" => {
-          let value: Vec<i64> = offset_mode_obj.get_named_property::<Vec<i64>>("value")?;
-          OffsetMode::Pixel(value[0], value[1])
-        }
-        "Percent" => {
-          let value: Vec<f64> = offset_mode_obj.get_named_property::<Vec<f64>>("value")?;
-          OffsetMode::Percent(value[0] as f32, value[1] as f32)
-        }
-        "Center" => OffsetMode::Center,
-        _ => {
-          return Err(Error::new(
-            Status::InvalidArg,
-            "Invalid OffsetMode type".to_string(),
-          ))
-        }
-      };
-      Ok(offset_mode)
-    } else {
-      Ok(OffsetMode::Center)
-    }
-  }
+#[derive(Clone, Copy)]
+pub struct Resize {
+  pub mode: ResizeMode,
+  pub filter: ResizeFilter,
+}
+
+#[derive(Clone)]
+pub struct Limits {
+  pub max_output_pixels: u64,
+  pub decode: image::io::Limits,
+}
+
+#[derive(Clone, Copy)]
+pub struct Placement {
+  pub offset: OffsetMode,
+  pub anchor: Anchor,
+  /// Keep the layer fully inside the canvas.
+  pub clamp: bool,
 }
 
 #[napi]
@@ -95,74 +92,541 @@ pub fn build_composited_image(
   product_buffer: Buffer,
   overlay_buffer: Buffer,
   options: BuildCompositedImageOptions,
-) {
-  let background_color: Vec<u8> = options.background_color.clone();
+) -> Result<Buffer, ErrorCode> {
+  let job = CompositeJob::from_build_options(product_buffer, overlay_buffer, &options)?;
+  job.run().map(Buffer::from)
+}
 
-  let product_image = image::load_from_memory(&product_buffer).unwrap();
-  let overlay_image = image::load_from_memory(&overlay_buffer).unwrap();
+#[napi]
+pub fn build_composited_image_async(
+  product_buffer: Buffer,
+  overlay_buffer: Buffer,
+  options: BuildCompositedImageOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<CompositeTask<Buffer>> {
+  let job = CompositeJob::from_build_options(product_buffer, overlay_buffer, &options);
+  AsyncTask::with_optional_signal(CompositeTask::new(job), signal)
+}
 
-  let product_image = match options.get_resize_mode().unwrap() {
-    Some(resize_mode) => resize_image(&product_image, resize_mode),
-    None => product_image,
-  };
+#[napi(object)]
+pub struct CompositeInfo {
+  pub buffer: Buffer,
+  /// Set when `product.autoTrim` is used.
+  pub product_trim: Option<TrimBox>,
+}
+
+impl From<Composite> for CompositeInfo {
+  fn from(composite: Composite) -> Self {
+    CompositeInfo {
+      // The product is the first layer of the `buildCompositedImage` preset.
+      product_trim: composite.trim_boxes.first().copied().flatten(),
+      buffer: composite.encoded.into(),
+    }
+  }
+}
+
+/// `buildCompositedImage`, also reporting what was computed along the way.
+#[napi]
+pub fn build_composited_image_with_info(
+  product_buffer: Buffer,
+  overlay_buffer: Buffer,
+  options: BuildCompositedImageOptions,
+) -> Result<CompositeInfo, ErrorCode> {
+  let job = CompositeJob::from_build_options(product_buffer, overlay_buffer, &options)?;
+  job.run().map(CompositeInfo::from)
+}
+
+#[napi]
+pub fn build_composited_image_with_info_async(
+  product_buffer: Buffer,
+  overlay_buffer: Buffer,
+  options: BuildCompositedImageOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<CompositeTask<CompositeInfo>> {
+  let job = CompositeJob::from_build_options(product_buffer, overlay_buffer, &options);
+  AsyncTask::with_optional_signal(CompositeTask::new(job), signal)
+}
+
+#[napi]
+pub fn compose_layers(
+  canvas: CanvasOptions,
+  layers: Vec<LayerOptions>,
+  options: Option<ComposeLayersOptions>,
+) -> Result<Buffer, ErrorCode> {
+  let job = CompositeJob::from_layers(&canvas, layers, options.as_ref())?;
+  job.run().map(Buffer::from)
+}
+
+#[napi]
+pub fn compose_layers_async(
+  canvas: CanvasOptions,
+  layers: Vec<LayerOptions>,
+  options: Option<ComposeLayersOptions>,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<CompositeTask<Buffer>> {
+  let job = CompositeJob::from_layers(&canvas, layers, options.as_ref());
+  AsyncTask::with_optional_signal(CompositeTask::new(job), signal)
+}
+
+/// One image in the composition stack, drawn in order from bottom to top.
+pub struct Layer {
+  pub buffer: Buffer,
+  /// Used in error messages, e.g. `layers[2]`.
+  pub name: String,
+  pub decode_error: ErrorCode,
+  pub resize: Option<Resize>,
+  pub placement: Placement,
+  pub opacity: f32,
+  pub blend_mode: BlendMode,
+  /// Apply the EXIF orientation right after decoding.
+  pub auto_orient: bool,
+  /// Applied after orienting, before resizing.
+  pub background_removal: Option<BackgroundRemoval>,
+  /// Applied after background removal, before resizing.
+  pub auto_trim: Option<AutoTrim>,
+  /// Applied after resizing, so placement and the shadow use the
+  /// transformed bounds.
+  pub transform: Transform,
+  /// Drawn under the layer, built from its rendered alpha.
+  pub shadow: Option<Shadow>,
+}
+
+impl Layer {
+  fn render(
+    &self,
+    limits: &Limits,
+    linear_light: bool,
+  ) -> Result<(RgbaImage, Option<TrimBox>), ErrorCode> {
+    let image = decode_image(&self.buffer, &self.name, self.decode_error, limits)?;
+    let image = if self.auto_orient {
+      auto_orient(image, &self.buffer)
+    } else {
+      image
+    };
+
+    let image = match &self.background_removal {
+      Some(removal) => {
+        let mut image = image.into_rgba8();
+        remove_background(&mut image, removal);
+        DynamicImage::ImageRgba8(image)
+      }
+      None => image,
+    };
+
+    let (image, trim_box) = match &self.auto_trim {
+      Some(trim) => {
+        let image = image.into_rgba8();
+        let trim_box = find_trim_box(&image, trim);
+        let trimmed = image::imageops::crop_imm(
+          &image,
+          trim_box.x,
+          trim_box.y,
+          trim_box.width,
+          trim_box.height,
+        );
+        (DynamicImage::ImageRgba8(trimmed.to_image()), Some(trim_box))
+      }
+      None => (image, None),
+    };
+
+    let image = match self.resize {
+      Some(resize) => resize_image(
+        &image,
+        resize,
+        &self.name,
+        limits.max_output_pixels,
+        linear_light,
+      )?,
+      None => image,
+    };
+
+    let size = self.transform.output_size(image.dimensions());
+    check_output_pixels(&self.name, size, limits.max_output_pixels)?;
+    Ok((
+      apply_transform(image.into_rgba8(), &self.transform),
+      trim_box,
+    ))
+  }
+}
+
+#[derive(Clone, Copy)]
+pub enum CanvasSize {
+  Fixed(u32, u32),
+  /// Size of the rendered layer at this index.
+  MatchLayer(usize),
+}
+
+/// Everything needed to render one image, resolved from the JS options so it
+/// can be moved off the main thread.
+pub struct CompositeJob {
+  canvas_size: CanvasSize,
+  background: Background,
+  layers: Vec<Layer>,
+  limits: Limits,
+  encoding: Encoding,
+  output_path: Option<String>,
+  /// Resize and blend in linear light.
+  linear_light: bool,
+}
+
+impl CompositeJob {
+  /// The `buildCompositedImage` preset: the product under the overlay, on a
+  /// canvas the size of the overlay unless `canvas` is set.
+  pub fn from_build_options(
+    product_buffer: Buffer,
+    overlay_buffer: Buffer,
+    options: &BuildCompositedImageOptions,
+  ) -> Result<Self, ErrorCode> {
+    let product = Layer {
+      buffer: product_buffer,
+      name: "product image".to_string(),
+      decode_error: ErrorCode::DecodeProductFailed,
+      resize: options.get_resize()?,
+      placement: options.get_product_placement()?,
+      opacity: options.get_product_opacity()?,
+      blend_mode: options.get_product_blend_mode(),
+      auto_orient: options.auto_orient.unwrap_or(true),
+      background_removal: options.get_product_background_removal()?,
+      auto_trim: options.get_product_auto_trim()?,
+      transform: options.get_product_transform()?,
+      shadow: options.get_product_shadow()?,
+    };
+    let overlay = Layer {
+      buffer: overlay_buffer,
+      name: "overlay image".to_string(),
+      decode_error: ErrorCode::DecodeOverlayFailed,
+      resize: None,
+      placement: options.get_overlay_placement()?,
+      opacity: options.get_overlay_opacity()?,
+      blend_mode: options.get_overlay_blend_mode(),
+      auto_orient: options.auto_orient.unwrap_or(true),
+      background_removal: None,
+      auto_trim: None,
+      transform: Transform::default(),
+      shadow: None,
+    };
+
+    let canvas_size = match options.get_canvas_size()? {
+      Some((width, height)) => CanvasSize::Fixed(width, height),
+      None => CanvasSize::MatchLayer(1),
+    };
+
+    Ok(CompositeJob {
+      canvas_size,
+      background: options.get_background()?,
+      layers: vec![product, overlay],
+      limits: get_limits(options.limits.as_ref()),
+      encoding: Encoding::from_options(options.output.as_ref())?,
+      output_path: options.output_path.clone(),
+      linear_light: options.linear_light.unwrap_or(false),
+    })
+  }
+
+  pub fn from_layers(
+    canvas: &CanvasOptions,
+    layers: Vec<LayerOptions>,
+    options: Option<&ComposeLayersOptions>,
+  ) -> Result<Self, ErrorCode> {
+    let (width, height) = canvas.get_size()?;
+    let layers = layers
+      .into_iter()
+      .enumerate()
+      .map(|(index, layer)| layer.into_layer(index))
+      .collect::<Result<Vec<_>, ErrorCode>>()?;
+
+    Ok(CompositeJob {
+      canvas_size: CanvasSize::Fixed(width, height),
+      background: canvas
+        .get_background()?
+        .unwrap_or(Background::Solid(Rgba([0, 0, 0, 0]))),
+      layers,
+      limits: get_limits(options.and_then(|options| options.limits.as_ref())),
+      encoding: Encoding::from_options(options.and_then(|options| options.output.as_ref()))?,
+      output_path: options.and_then(|options| options.output_path.clone()),
+      linear_light: options
+        .and_then(|options| options.linear_light)
+        .unwrap_or(false),
+    })
+  }
 
-  let (width, height) = overlay_image.dimensions();
+  pub fn run(&self) -> Result<Composite, ErrorCode> {
+    let (images, trim_boxes): (Vec<_>, Vec<_>) = self
+      .layers
+      .iter()
+      .map(|layer| layer.render(&self.limits, self.linear_light))
+      .collect::<Result<Vec<_>, ErrorCode>>()?
+      .into_iter()
+      .unzip();
 
-  let color = Rgba(background_color.as_slice().try_into().unwrap());
-  let mut background: RgbaImage = ImageBuffer::from_pixel(width, height, color);
+    let (width, height) = match self.canvas_size {
+      CanvasSize::Fixed(width, height) => (width, height),
+      CanvasSize::MatchLayer(index) => images[index].dimensions(),
+    };
+    check_output_pixels("canvas", (width, height), self.limits.max_output_pixels)?;
+    let mut background = self
+      .background
+      .render(width, height, &self.limits, self.linear_light)?;
+
+    compose(&mut background, &self.layers, &images, self.linear_light);
+
+    let encoded = self.encoding.encode(&background)?;
+
+    if let Some(output_path) = &self.output_path {
+      std::fs::write(output_path, &encoded).map_err(|err| {
+        Error::new(
+          ErrorCode::WriteOutputFailed,
+          format!("Failed to write {}: {}", output_path, err),
+        )
+      })?;
+    }
 
-  let offset = options.get_offset_mode().unwrap();
+    Ok(Composite {
+      encoded,
+      trim_boxes,
+    })
+  }
+}
 
-  compose(&mut background, &product_image, &overlay_image, offset);
+/// Output of a [`CompositeJob`].
+pub struct Composite {
+  pub encoded: Vec<u8>,
+  /// One entry per layer, set for layers that were auto-trimmed.
+  pub trim_boxes: Vec<Option<TrimBox>>,
+}
 
-  background.save("./result.png").unwrap();
+impl From<Composite> for Buffer {
+  fn from(composite: Composite) -> Self {
+    composite.encoded.into()
+  }
 }
 
 fn compose(
   background_img: &mut RgbaImage,
-  product_img: &DynamicImage,
-  overlay_img: &DynamicImage,
-  product_offset: OffsetMode,
+  layers: &[Layer],
+  images: &[RgbaImage],
+  linear_light: bool,
 ) {
   let base_size = background_img.dimensions();
 
-  let product_size = product_img.dimensions();
-  let (product_x, product_y) = calculate_position(product_offset, product_size, base_size);
-  overlay(background_img, product_img, product_x, product_y);
-
-  let overlay_size = overlay_img.dimensions();
-  let overlay_x = ((base_size.0.saturating_sub(overlay_size.0)) / 2) as i64;
-  let overlay_y = ((base_size.1.saturating_sub(overlay_size.1)) / 2) as i64;
-  overlay(background_img, overlay_img, overlay_x, overlay_y);
+  for (layer, image) in layers.iter().zip(images) {
+    let (x, y) = calculate_position(layer.placement, image.dimensions(), base_size);
+    if let Some(shadow) = &layer.shadow {
+      let padding = shadow.padding() as i64;
+      blend(
+        background_img,
+        &render_shadow(image, shadow),
+        x.saturating_add(shadow.offset_x).saturating_sub(padding),
+        y.saturating_add(shadow.offset_y).saturating_sub(padding),
+        shadow.opacity * layer.opacity,
+        BlendMode::Normal,
+        linear_light,
+      );
+    }
+    blend(
+      background_img,
+      image,
+      x,
+      y,
+      layer.opacity,
+      layer.blend_mode,
+      linear_light,
+    );
+  }
 }
 
 fn calculate_position(
-  offset: OffsetMode,
+  placement: Placement,
   extra_size: (u32, u32),
   base_size: (u32, u32),
 ) -> (i64, i64) {
-  match offset {
+  let halves = anchor_halves(placement.anchor);
+
+  let (x_pos, y_pos) = match placement.offset {
     OffsetMode::Pixel(x, y) => {
-      let x_pos = x - extra_size.0 as i64 / 2;
-      let y_pos = y - extra_size.1 as i64 / 2;
+      let x_pos = x.saturating_sub(extra_size.0 as i64 * halves / 2);
+      let y_pos = y.saturating_sub(extra_size.1 as i64 * halves / 2);
       (x_pos, y_pos)
     }
     OffsetMode::Percent(x_percent, y_percent) => {
-      let x_pos = (base_size.0 as f32 * x_percent / 100.0) - extra_size.0 as f32 / 2.0;
-      let y_pos = (base_size.1 as f32 * y_percent / 100.0) - extra_size.1 as f32 / 2.0;
+      let x_pos =
+        (base_size.0 as f32 * x_percent / 100.0) - extra_size.0 as f32 * halves as f32 / 2.0;
+      let y_pos =
+        (base_size.1 as f32 * y_percent / 100.0) - extra_size.1 as f32 * halves as f32 / 2.0;
       (x_pos as i64, y_pos as i64)
     }
     OffsetMode::Center => (
       ((base_size.0.saturating_sub(extra_size.0)) / 2) as i64,
       ((base_size.1.saturating_sub(extra_size.1)) / 2) as i64,
     ),
+  };
+
+  if placement.clamp {
+    (
+      clamp_axis(x_pos, extra_size.0, base_size.0),
+      clamp_axis(y_pos, extra_size.1, base_size.1),
+    )
+  } else {
+    (x_pos, y_pos)
+  }
+}
+
+// Distance from the layer's top-left corner to its anchor, in halves of its size.
+fn anchor_halves(anchor: Anchor) -> i64 {
+  match anchor {
+    Anchor::TopLeft => 0,
+    Anchor::Center => 1,
+    Anchor::BottomRight => 2,
   }
 }
 
-fn resize_image(image: &DynamicImage, mode: ResizeMode) -> DynamicImage {
-  let (original_width, original_height) = image.dimensions();
+// A layer larger than the canvas is kept covering it instead.
+fn clamp_axis(pos: i64, extra: u32, base: u32) -> i64 {
+  let max_pos = base as i64 - extra as i64;
+  pos.clamp(max_pos.min(0), max_pos.max(0))
+}
+
+fn decode_image(
+  buffer: &[u8],
+  name: &str,
+  decode_error: ErrorCode,
+  limits: &Limits,
+) -> Result<DynamicImage, ErrorCode> {
+  // Colors are converted to sRGB here, so every layer and background
+  // composites in the same space.
+  let decode = || -> ImageResult<DynamicImage> {
+    let mut reader = image::io::Reader::new(Cursor::new(buffer)).with_guessed_format()?;
+    let format = reader.format();
+    if format == Some(ImageFormat::Jpeg) {
+      if let Some(image) = icc::decode_cmyk_jpeg(buffer, &limits.decode)? {
+        return Ok(image);
+      }
+    }
+    reader.limits(limits.decode.clone());
+    let image = reader.decode()?;
+    Ok(match format {
+      Some(format) => icc::convert_to_srgb(image, buffer, format),
+      None => image,
+    })
+  };
+
+  decode().map_err(|err| match err {
+    ImageError::Limits(_) => Error::new(
+      ErrorCode::InputTooLarge,
+      format!("{} exceeds the decode limits: {}", name, err),
+    ),
+    _ => Error::new(decode_error, format!("Failed to decode {}: {}", name, err)),
+  })
+}
+
+fn resize_image(
+  image: &DynamicImage,
+  resize: Resize,
+  name: &str,
+  max_output_pixels: u64,
+  linear_light: bool,
+) -> Result<DynamicImage, ErrorCode> {
+  let original_size = image.dimensions();
+  let (new_width, new_height) = resize_dimensions(original_size, resize.mode);
+
+  if new_width == 0 || new_height == 0 {
+    return Err(Error::new(
+      ErrorCode::InvalidResizeMode,
+      format!(
+        "Resizing {} from {}x{} gives an empty {}x{} image",
+        name, original_size.0, original_size.1, new_width, new_height
+      ),
+    ));
+  }
+  check_output_pixels(name, (new_width, new_height), max_output_pixels)?;
 
-  let (new_width, new_height) = match mode {
+  let resized = if (new_width, new_height) == original_size {
+    image.clone()
+  } else {
+    let filter = filter_type(resize.filter, original_size, (new_width, new_height));
+    resample(image, (new_width, new_height), filter, linear_light)
+  };
+
+  let resized = match resize.mode {
+    ResizeMode::Cover(width, height) => {
+      let x = new_width.saturating_sub(width) / 2;
+      let y = new_height.saturating_sub(height) / 2;
+      resized.crop_imm(x, y, width, height)
+    }
+    _ => resized,
+  };
+  Ok(resized)
+}
+
+// Images with alpha are resampled premultiplied, so the color of transparent
+// pixels doesn't bleed into the edges around them.
+fn resample(
+  image: &DynamicImage,
+  (width, height): (u32, u32),
+  filter: FilterType,
+  linear_light: bool,
+) -> DynamicImage {
+  let has_alpha = image.color().has_alpha();
+  if !has_alpha && !linear_light {
+    return image.resize_exact(width, height, filter);
+  }
+
+  let mut pixels = if linear_light {
+    linear::linearize(image)
+  } else {
+    image.to_rgba32f()
+  };
+  if has_alpha {
+    for pixel in pixels.pixels_mut() {
+      for channel in 0..3 {
+        pixel[channel] *= pixel[3];
+      }
+    }
+  }
+
+  let mut resized = image::imageops::resize(&pixels, width, height, filter);
+  if has_alpha {
+    for pixel in resized.pixels_mut() {
+      // Sharper filters can ring past the 0-1 range.
+      pixel[3] = pixel[3].clamp(0.0, 1.0);
+      for channel in 0..3 {
+        pixel[channel] = if pixel[3] > 0.0 {
+          (pixel[channel] / pixel[3]).clamp(0.0, 1.0)
+        } else {
+          0.0
+        };
+      }
+    }
+  }
+
+  if linear_light {
+    DynamicImage::ImageRgba8(linear::delinearize(&resized))
+  } else {
+    DynamicImage::ImageRgba32F(resized).into_rgba8().into()
+  }
+}
+
+fn check_output_pixels(
+  name: &str,
+  size: (u32, u32),
+  max_output_pixels: u64,
+) -> Result<(), ErrorCode> {
+  let pixels = size.0 as u64 * size.1 as u64;
+  if pixels > max_output_pixels {
+    return Err(Error::new(
+      ErrorCode::LimitExceeded,
+      format!(
+        "{} would be {}x{} ({} pixels), over the limit of {}",
+        name, size.0, size.1, pixels, max_output_pixels
+      ),
+    ));
+  }
+  Ok(())
+}
+
+fn resize_dimensions(original_size: (u32, u32), mode: ResizeMode) -> (u32, u32) {
+  let (original_width, original_height) = original_size;
+
+  match mode {
     ResizeMode::Width(target_width) => {
       let aspect_ratio = original_height as f32 / original_width as f32;
       let new_height = (target_width as f32 * aspect_ratio) as u32;
@@ -178,7 +642,57 @@ fn resize_image(image: &DynamicImage, mode: ResizeMode) -> DynamicImage {
       let new_height = (original_height as f32 * factor) as u32;
       (new_width, new_height)
     }
-  };
+    ResizeMode::Fit(width, height) => {
+      // Rounded rather than truncated, and at least 1 pixel, so a very thin
+      // image still fits instead of coming out empty.
+      let scale =
+        (width as f64 / original_width as f64).min(height as f64 / original_height as f64);
+      let fit = |original: u32| ((original as f64 * scale).round() as u32).max(1);
+      (fit(original_width), fit(original_height))
+    }
+    ResizeMode::Cover(width, height) => {
+      // One scale for both sides, rounded up, so neither side can fall short
+      // of the box before it is cropped to it.
+      let scale =
+        (width as f64 / original_width as f64).max(height as f64 / original_height as f64);
+      let cover =
+        |original: u32, target: u32| ((original as f64 * scale).ceil() as u32).max(target);
+      (cover(original_width, width), cover(original_height, height))
+    }
+    ResizeMode::Exact(width, height) => (width, height),
+    ResizeMode::Max(width, height) => {
+      if original_width <= width && original_height <= height {
+        original_size
+      } else {
+        resize_dimensions(original_size, ResizeMode::Fit(width, height))
+      }
+    }
+  }
+}
 
-  image.resize_exact(new_width, new_height, FilterType::Lanczos3)
+fn filter_type(
+  filter: ResizeFilter,
+  original_size: (u32, u32),
+  new_size: (u32, u32),
+) -> FilterType {
+  match filter {
+    ResizeFilter::Nearest => FilterType::Nearest,
+    ResizeFilter::Triangle => FilterType::Triangle,
+    ResizeFilter::CatmullRom => FilterType::CatmullRom,
+    ResizeFilter::Gaussian => FilterType::Gaussian,
+    ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+    ResizeFilter::Auto => {
+      // Wide kernels get expensive on large downscales, where their extra
+      // sharpness is lost anyway.
+      let factor = (new_size.0 as f32 / original_size.0 as f32)
+        .max(new_size.1 as f32 / original_size.1 as f32);
+      if factor < 0.25 {
+        FilterType::Triangle
+      } else if factor < 0.5 {
+        FilterType::CatmullRom
+      } else {
+        FilterType::Lanczos3
+      }
+    }
+  }
 }
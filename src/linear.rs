@@ -0,0 +1,59 @@
+use std::sync::OnceLock;
+
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+
+/// Decodes an 8-bit sRGB channel to linear light in 0-1.
+pub fn to_linear(value: u8) -> f32 {
+  static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+  let table = TABLE.get_or_init(|| {
+    let mut table = [0.0; 256];
+    for (value, linear) in table.iter_mut().enumerate() {
+      let encoded = value as f32 / 255.0;
+      *linear = if encoded <= 0.04045 {
+        encoded / 12.92
+      } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+      };
+    }
+    table
+  });
+  table[value as usize]
+}
+
+/// Encodes a linear light value in 0-1 back to an 8-bit sRGB channel.
+pub fn from_linear(value: f32) -> u8 {
+  let value = value.clamp(0.0, 1.0);
+  let encoded = if value <= 0.0031308 {
+    value * 12.92
+  } else {
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+  };
+  (encoded * 255.0).round() as u8
+}
+
+/// Converts to f32 with linear light color channels. Alpha is already linear.
+pub fn linearize(image: &DynamicImage) -> Rgba32FImage {
+  let image = image.to_rgba8();
+  Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+    let Rgba([red, green, blue, alpha]) = *image.get_pixel(x, y);
+    Rgba([
+      to_linear(red),
+      to_linear(green),
+      to_linear(blue),
+      alpha as f32 / 255.0,
+    ])
+  })
+}
+
+/// The inverse of [`linearize`].
+pub fn delinearize(image: &Rgba32FImage) -> RgbaImage {
+  RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+    let Rgba([red, green, blue, alpha]) = *image.get_pixel(x, y);
+    Rgba([
+      from_linear(red),
+      from_linear(green),
+      from_linear(blue),
+      (alpha.clamp(0.0, 1.0) * 255.0).round() as u8,
+    ])
+  })
+}
@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+
+use image::{Rgba, RgbaImage};
+
+/// Which pixels close to the key color are removed.
+#[napi(string_enum)]
+pub enum BackgroundRemovalMode {
+  /// Only pixels connected to the image border, so matching colors inside
+  /// the subject are kept.
+  Flood,
+  /// Every matching pixel.
+  Global,
+}
+
+#[derive(Clone, Copy)]
+pub struct BackgroundRemoval {
+  pub color: Rgba<u8>,
+  /// Largest per-channel difference from `color` that is fully removed.
+  pub tolerance: f32,
+  /// Width of the band past `tolerance` that fades from transparent to opaque.
+  pub feather: f32,
+  pub mode: BackgroundRemovalMode,
+}
+
+/// Makes pixels close to the key color transparent in place.
+pub fn remove_background(image: &mut RgbaImage, removal: &BackgroundRemoval) {
+  match removal.mode {
+    BackgroundRemovalMode::Global => {
+      for pixel in image.pixels_mut() {
+        key_pixel(pixel, removal);
+      }
+    }
+    BackgroundRemovalMode::Flood => flood_from_edges(image, removal),
+  }
+}
+
+fn flood_from_edges(image: &mut RgbaImage, removal: &BackgroundRemoval) {
+  let (width, height) = image.dimensions();
+  let mut visited = vec![false; width as usize * height as usize];
+  let mut queue = VecDeque::new();
+
+  let mut visit = |x: u32, y: u32, image: &RgbaImage, queue: &mut VecDeque<(u32, u32)>| {
+    let index = y as usize * width as usize + x as usize;
+    if !visited[index] && key_coverage(image.get_pixel(x, y), removal) < 1.0 {
+      visited[index] = true;
+      queue.push_back((x, y));
+    }
+  };
+
+  for x in 0..width {
+    visit(x, 0, image, &mut queue);
+    visit(x, height - 1, image, &mut queue);
+  }
+  for y in 0..height {
+    visit(0, y, image, &mut queue);
+    visit(width - 1, y, image, &mut queue);
+  }
+
+  while let Some((x, y)) = queue.pop_front() {
+    if x > 0 {
+      visit(x - 1, y, image, &mut queue);
+    }
+    if x + 1 < width {
+      visit(x + 1, y, image, &mut queue);
+    }
+    if y > 0 {
+      visit(x, y - 1, image, &mut queue);
+    }
+    if y + 1 < height {
+      visit(x, y + 1, image, &mut queue);
+    }
+    key_pixel(image.get_pixel_mut(x, y), removal);
+  }
+}
+
+// 0 for the key color, 1 once the pixel is `tolerance + feather` away from it.
+fn key_coverage(pixel: &Rgba<u8>, removal: &BackgroundRemoval) -> f32 {
+  let distance = (0..3)
+    .map(|channel| (pixel[channel] as f32 - removal.color[channel] as f32).abs())
+    .fold(0.0, f32::max);
+
+  if distance <= removal.tolerance {
+    0.0
+  } else if distance >= removal.tolerance + removal.feather {
+    1.0
+  } else {
+    (distance - removal.tolerance) / removal.feather
+  }
+}
+
+fn key_pixel(pixel: &mut Rgba<u8>, removal: &BackgroundRemoval) {
+  let coverage = key_coverage(pixel, removal);
+  if coverage >= 1.0 {
+    return;
+  }
+  if coverage > 0.0 {
+    // Take the key color back out of the fringe so it doesn't leave a halo.
+    for channel in 0..3 {
+      let key = removal.color[channel] as f32;
+      let color = (pixel[channel] as f32 - key * (1.0 - coverage)) / coverage;
+      pixel[channel] = color.round().clamp(0.0, 255.0) as u8;
+    }
+  }
+  pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+}
@@ -0,0 +1,741 @@
+use image::Rgba;
+use napi::bindgen_prelude::Buffer;
+use napi::{Either, Error, Result};
+
+use crate::{
+  parse_color_input, AutoTrim, Background, BackgroundRemoval, BackgroundRemovalMode, BlendMode,
+  ErrorCode, GradientStop, Layer, Limits, OffsetMode, OutputOptions, Placement, Resize, ResizeMode,
+  Shadow, Transform,
+};
+
+const DEFAULT_MAX_OUTPUT_PIXELS: u64 = 100_000_000;
+// Pixel and percent offsets, kept small enough that positions can't overflow.
+const MAX_OFFSET: f64 = i32::MAX as f64;
+const DEFAULT_KEY_TOLERANCE: f64 = 16.0;
+const DEFAULT_KEY_FEATHER: f64 = 16.0;
+const DEFAULT_TRIM_TOLERANCE: f64 = 8.0;
+const DEFAULT_SHADOW_BLUR_RADIUS: f64 = 10.0;
+const DEFAULT_SHADOW_OPACITY: f64 = 0.5;
+const MAX_SHADOW_BLUR_RADIUS: f64 = 250.0;
+const DEFAULT_GRADIENT_ANGLE: f64 = 180.0;
+
+#[napi(string_enum)]
+pub enum ResizeModeType {
+  Width,
+  Height,
+  Scale,
+  /// Largest size that fits inside `width` x `height`.
+  Fit,
+  /// Smallest size that covers `width` x `height`, cropped to it.
+  Cover,
+  /// Exactly `width` x `height`, ignoring the aspect ratio.
+  Exact,
+  /// Like `Fit`, but never enlarges the image.
+  Max,
+}
+
+#[napi(string_enum)]
+pub enum ResizeFilter {
+  Nearest,
+  Triangle,
+  CatmullRom,
+  Gaussian,
+  Lanczos3,
+  /// `Lanczos3`, falling back to cheaper filters for large downscales.
+  Auto,
+}
+
+#[napi(object, js_name = "ResizeMode")]
+pub struct ResizeModeOptions {
+  pub r#type: ResizeModeType,
+  /// Pixels for `Width` and `Height`, a factor for `Scale`.
+  pub value: Option<f64>,
+  /// Box width for `Fit`, `Cover`, `Exact` and `Max`.
+  pub width: Option<f64>,
+  /// Box height for `Fit`, `Cover`, `Exact` and `Max`.
+  pub height: Option<f64>,
+  /// Defaults to `Lanczos3`.
+  pub filter: Option<ResizeFilter>,
+}
+
+#[napi(string_enum)]
+pub enum OffsetModeType {
+  Pixel,
+  Percent,
+  Center,
+}
+
+/// Point of the layer that an offset refers to.
+#[napi(string_enum)]
+pub enum Anchor {
+  TopLeft,
+  Center,
+  BottomRight,
+}
+
+#[napi(object, js_name = "OffsetMode")]
+pub struct OffsetModeOptions {
+  pub r#type: OffsetModeType,
+  /// `[x, y]` of the layer anchor, required for `Pixel` and `Percent`.
+  pub value: Option<Vec<f64>>,
+  /// Defaults to `Center`. Ignored by the `Center` type.
+  pub anchor: Option<Anchor>,
+  /// Keep the layer fully inside the canvas. Defaults to `false`.
+  pub clamp: Option<bool>,
+}
+
+#[napi(string_enum)]
+pub enum BackgroundType {
+  Solid,
+  LinearGradient,
+  RadialGradient,
+  Image,
+}
+
+/// How a background image is scaled to the canvas.
+#[napi(string_enum)]
+pub enum BackgroundFit {
+  /// Fills the canvas, cropping what overflows.
+  Cover,
+  /// Fits inside the canvas, centered on `color`.
+  Contain,
+}
+
+#[napi(object, js_name = "GradientStop")]
+pub struct GradientStopOptions {
+  /// 0-1 along the gradient.
+  pub offset: f64,
+  pub color: Either<String, Vec<f64>>,
+}
+
+#[napi(object, js_name = "Background")]
+pub struct BackgroundOptions {
+  pub r#type: BackgroundType,
+  /// Required for `Solid`. Behind the image for `Image`, defaults to
+  /// transparent there.
+  pub color: Option<Either<String, Vec<f64>>>,
+  /// Required for the gradients, sorted by offset.
+  pub stops: Option<Vec<GradientStopOptions>>,
+  /// `LinearGradient` direction in degrees, clockwise from "to top" as in
+  /// CSS. Defaults to 180, top to bottom.
+  pub angle: Option<f64>,
+  /// Encoded image, required for `Image`.
+  pub buffer: Option<Buffer>,
+  /// Defaults to `Cover`.
+  pub fit: Option<BackgroundFit>,
+}
+
+#[napi(object)]
+pub struct CanvasOptions {
+  pub width: f64,
+  pub height: f64,
+  /// Used when `background` is not set.
+  pub background_color: Option<Either<String, Vec<f64>>>,
+  /// Defaults to the top-level `background` or `backgroundColor` in
+  /// `buildCompositedImage` and to transparent in `composeLayers`.
+  pub background: Option<BackgroundOptions>,
+}
+
+impl CanvasOptions {
+  pub fn get_size(&self) -> Result<(u32, u32), ErrorCode> {
+    Ok((
+      parse_dimension("canvas.width", self.width, ErrorCode::InvalidCanvas)?,
+      parse_dimension("canvas.height", self.height, ErrorCode::InvalidCanvas)?,
+    ))
+  }
+
+  pub fn get_background(&self) -> Result<Option<Background>, ErrorCode> {
+    if let Some(background) = &self.background {
+      return parse_background("canvas.background", background).map(Some);
+    }
+    self
+      .background_color
+      .as_ref()
+      .map(|color| parse_color("canvas.backgroundColor", color).map(Background::Solid))
+      .transpose()
+  }
+}
+
+#[napi(object)]
+pub struct LayerOptions {
+  pub buffer: Buffer,
+  pub resize_mode: Option<ResizeModeOptions>,
+  /// Defaults to centering the layer on the canvas.
+  pub offset_mode: Option<OffsetModeOptions>,
+  /// 0-1, defaults to 1.
+  pub opacity: Option<f64>,
+  /// Defaults to `Normal`.
+  pub blend_mode: Option<BlendMode>,
+  /// Rotate or flip the image by its EXIF orientation. Defaults to `true`.
+  pub auto_orient: Option<bool>,
+}
+
+impl LayerOptions {
+  pub fn into_layer(self, index: usize) -> Result<Layer, ErrorCode> {
+    let field = format!("layers[{}]", index);
+    Ok(Layer {
+      resize: parse_resize(&format!("{}.resizeMode", field), self.resize_mode.as_ref())?,
+      placement: parse_placement(&format!("{}.offsetMode", field), self.offset_mode.as_ref())?,
+      opacity: parse_opacity(&format!("{}.opacity", field), self.opacity)?,
+      blend_mode: self.blend_mode.unwrap_or(BlendMode::Normal),
+      auto_orient: self.auto_orient.unwrap_or(true),
+      background_removal: None,
+      auto_trim: None,
+      shadow: None,
+      transform: Transform::default(),
+      buffer: self.buffer,
+      name: field,
+      decode_error: ErrorCode::DecodeLayerFailed,
+    })
+  }
+}
+
+#[napi(object)]
+pub struct LimitsOptions {
+  /// Largest canvas or resized layer, in pixels. Defaults to 100 megapixels.
+  pub max_output_pixels: Option<u32>,
+  /// Largest input image width accepted by the decoder.
+  pub max_width: Option<u32>,
+  /// Largest input image height accepted by the decoder.
+  pub max_height: Option<u32>,
+  /// Most memory the decoder may allocate per image, in bytes. Defaults to 512 MiB.
+  pub max_alloc_bytes: Option<u32>,
+}
+
+pub fn get_limits(limits: Option<&LimitsOptions>) -> Limits {
+  let mut decode = image::io::Limits::default();
+  if let Some(limits) = limits {
+    decode.max_image_width = limits.max_width;
+    decode.max_image_height = limits.max_height;
+    if let Some(max_alloc_bytes) = limits.max_alloc_bytes {
+      decode.max_alloc = Some(max_alloc_bytes.into());
+    }
+  }
+
+  Limits {
+    max_output_pixels: limits
+      .and_then(|limits| limits.max_output_pixels)
+      .map_or(DEFAULT_MAX_OUTPUT_PIXELS, u64::from),
+    decode,
+  }
+}
+
+#[napi(object)]
+pub struct ComposeLayersOptions {
+  /// Resize and blend in linear light instead of on gamma-encoded sRGB
+  /// values. Slower, but downscaled edges don't darken. Defaults to `false`.
+  pub linear_light: Option<bool>,
+  pub limits: Option<LimitsOptions>,
+  pub output: Option<OutputOptions>,
+  pub output_path: Option<String>,
+}
+
+#[napi(object)]
+pub struct RemoveBackgroundOptions {
+  /// Key color, alpha is ignored. Defaults to white.
+  pub color: Option<Either<String, Vec<f64>>>,
+  /// 0-255, the largest per-channel difference that is fully removed. Defaults to 16.
+  pub tolerance: Option<f64>,
+  /// 0-255, how far past `tolerance` the alpha fades back in. Defaults to 16.
+  pub feather: Option<f64>,
+  /// Defaults to `Flood`.
+  pub mode: Option<BackgroundRemovalMode>,
+}
+
+#[napi(object)]
+pub struct AutoTrimOptions {
+  /// 0-255, how far a pixel may be from the border color, or from fully
+  /// transparent, and still be trimmed. Defaults to 8.
+  pub tolerance: Option<f64>,
+}
+
+#[napi(object)]
+pub struct ShadowOptions {
+  /// Pixels, defaults to 0.
+  pub offset_x: Option<i32>,
+  /// Pixels, defaults to 0.
+  pub offset_y: Option<i32>,
+  /// 0-250 pixels, defaults to 10.
+  pub blur_radius: Option<f64>,
+  /// Defaults to black.
+  pub color: Option<Either<String, Vec<f64>>>,
+  /// 0-1, defaults to 0.5.
+  pub opacity: Option<f64>,
+}
+
+#[napi(object)]
+pub struct ProductOptions {
+  /// 0-1, defaults to 1.
+  pub opacity: Option<f64>,
+  /// Defaults to `Normal`.
+  pub blend_mode: Option<BlendMode>,
+  /// Makes the product's background transparent before compositing.
+  pub remove_background: Option<RemoveBackgroundOptions>,
+  /// Crops uniform or transparent margins before resizing and placement.
+  pub auto_trim: Option<AutoTrimOptions>,
+  /// Drop shadow drawn beneath the product.
+  pub shadow: Option<ShadowOptions>,
+  /// Degrees clockwise, applied after resizing. The product grows to its
+  /// rotated bounds, which are used for positioning. Defaults to 0.
+  pub rotate: Option<f64>,
+  /// Mirror the product horizontally, before rotating. Defaults to `false`.
+  pub flip_h: Option<bool>,
+  /// Mirror the product vertically, before rotating. Defaults to `false`.
+  pub flip_v: Option<bool>,
+}
+
+#[napi(object)]
+pub struct OverlayOptions {
+  /// 0-1, defaults to 1.
+  pub opacity: Option<f64>,
+  /// Defaults to `Normal`.
+  pub blend_mode: Option<BlendMode>,
+}
+
+#[napi(object)]
+pub struct BuildCompositedImageOptions {
+  /// Used when `background` is not set. Defaults to transparent.
+  pub background_color: Option<Either<String, Vec<f64>>>,
+  pub background: Option<BackgroundOptions>,
+  /// Defaults to the overlay dimensions.
+  pub canvas: Option<CanvasOptions>,
+  pub resize_mode: Option<ResizeModeOptions>,
+  pub offset_mode: Option<OffsetModeOptions>,
+  /// Defaults to centering the overlay on the canvas.
+  pub overlay_offset_mode: Option<OffsetModeOptions>,
+  pub product: Option<ProductOptions>,
+  pub overlay: Option<OverlayOptions>,
+  /// Rotate or flip the product and overlay by their EXIF orientation.
+  /// Defaults to `true`.
+  pub auto_orient: Option<bool>,
+  /// Resize and blend in linear light instead of on gamma-encoded sRGB
+  /// values. Slower, but downscaled edges don't darken. Defaults to `false`.
+  pub linear_light: Option<bool>,
+  pub limits: Option<LimitsOptions>,
+  pub output: Option<OutputOptions>,
+  pub output_path: Option<String>,
+}
+
+impl BuildCompositedImageOptions {
+  pub fn get_background(&self) -> Result<Background, ErrorCode> {
+    if let Some(background) = self.canvas.as_ref().map(CanvasOptions::get_background) {
+      if let Some(background) = background? {
+        return Ok(background);
+      }
+    }
+    if let Some(background) = &self.background {
+      return parse_background("background", background);
+    }
+    match &self.background_color {
+      Some(color) => parse_color("backgroundColor", color).map(Background::Solid),
+      None => Ok(Background::Solid(Rgba([0, 0, 0, 0]))),
+    }
+  }
+
+  pub fn get_canvas_size(&self) -> Result<Option<(u32, u32)>, ErrorCode> {
+    self
+      .canvas
+      .as_ref()
+      .map(CanvasOptions::get_size)
+      .transpose()
+  }
+
+  pub fn get_resize(&self) -> Result<Option<Resize>, ErrorCode> {
+    parse_resize("resizeMode", self.resize_mode.as_ref())
+  }
+
+  pub fn get_product_opacity(&self) -> Result<f32, ErrorCode> {
+    parse_opacity(
+      "product.opacity",
+      self.product.as_ref().and_then(|product| product.opacity),
+    )
+  }
+
+  pub fn get_overlay_opacity(&self) -> Result<f32, ErrorCode> {
+    parse_opacity(
+      "overlay.opacity",
+      self.overlay.as_ref().and_then(|overlay| overlay.opacity),
+    )
+  }
+
+  pub fn get_product_blend_mode(&self) -> BlendMode {
+    self
+      .product
+      .as_ref()
+      .and_then(|product| product.blend_mode)
+      .unwrap_or(BlendMode::Normal)
+  }
+
+  pub fn get_overlay_blend_mode(&self) -> BlendMode {
+    self
+      .overlay
+      .as_ref()
+      .and_then(|overlay| overlay.blend_mode)
+      .unwrap_or(BlendMode::Normal)
+  }
+
+  pub fn get_product_background_removal(&self) -> Result<Option<BackgroundRemoval>, ErrorCode> {
+    self
+      .product
+      .as_ref()
+      .and_then(|product| product.remove_background.as_ref())
+      .map(|options| parse_background_removal("product.removeBackground", options))
+      .transpose()
+  }
+
+  pub fn get_product_auto_trim(&self) -> Result<Option<AutoTrim>, ErrorCode> {
+    self
+      .product
+      .as_ref()
+      .and_then(|product| product.auto_trim.as_ref())
+      .map(|options| parse_auto_trim("product.autoTrim", options))
+      .transpose()
+  }
+
+  pub fn get_product_shadow(&self) -> Result<Option<Shadow>, ErrorCode> {
+    self
+      .product
+      .as_ref()
+      .and_then(|product| product.shadow.as_ref())
+      .map(|options| parse_shadow("product.shadow", options))
+      .transpose()
+  }
+
+  pub fn get_product_transform(&self) -> Result<Transform, ErrorCode> {
+    match &self.product {
+      Some(product) => parse_transform("product", product),
+      None => Ok(Transform::default()),
+    }
+  }
+
+  pub fn get_product_placement(&self) -> Result<Placement, ErrorCode> {
+    parse_placement("offsetMode", self.offset_mode.as_ref())
+  }
+
+  pub fn get_overlay_placement(&self) -> Result<Placement, ErrorCode> {
+    parse_placement("overlayOffsetMode", self.overlay_offset_mode.as_ref())
+  }
+}
+
+fn parse_resize(
+  field: &str,
+  resize_mode: Option<&ResizeModeOptions>,
+) -> Result<Option<Resize>, ErrorCode> {
+  let resize_mode = match resize_mode {
+    Some(resize_mode) => resize_mode,
+    None => return Ok(None),
+  };
+
+  let invalid = |reason: String| Error::new(ErrorCode::InvalidResizeMode, reason);
+  let value = || {
+    resize_mode.value.ok_or_else(|| {
+      invalid(format!(
+        "{}.value is required for Width, Height and Scale",
+        field
+      ))
+    })
+  };
+  let dimension = |name: &str, value: f64| {
+    parse_dimension(
+      &format!("{}.{}", field, name),
+      value,
+      ErrorCode::InvalidResizeMode,
+    )
+  };
+  let pixels = || dimension("value", value()?);
+  let size = || match (resize_mode.width, resize_mode.height) {
+    (Some(width), Some(height)) => Ok((dimension("width", width)?, dimension("height", height)?)),
+    _ => Err(invalid(format!(
+      "{}.width and {}.height are required for Fit, Cover, Exact and Max",
+      field, field
+    ))),
+  };
+
+  let mode = match resize_mode.r#type {
+    ResizeModeType::Width => ResizeMode::Width(pixels()?),
+    ResizeModeType::Height => ResizeMode::Height(pixels()?),
+    ResizeModeType::Scale => {
+      let factor = value()?;
+      if !(factor.is_finite() && factor > 0.0) {
+        return Err(invalid(format!(
+          "{}.value must be a positive scale factor, got {}",
+          field, factor
+        )));
+      }
+      ResizeMode::Scale(factor as f32)
+    }
+    ResizeModeType::Fit => {
+      let (width, height) = size()?;
+      ResizeMode::Fit(width, height)
+    }
+    ResizeModeType::Cover => {
+      let (width, height) = size()?;
+      ResizeMode::Cover(width, height)
+    }
+    ResizeModeType::Exact => {
+      let (width, height) = size()?;
+      ResizeMode::Exact(width, height)
+    }
+    ResizeModeType::Max => {
+      let (width, height) = size()?;
+      ResizeMode::Max(width, height)
+    }
+  };
+  Ok(Some(Resize {
+    mode,
+    filter: resize_mode.filter.unwrap_or(ResizeFilter::Lanczos3),
+  }))
+}
+
+fn parse_background_removal(
+  field: &str,
+  options: &RemoveBackgroundOptions,
+) -> Result<BackgroundRemoval, ErrorCode> {
+  let color = match &options.color {
+    Some(color) => parse_color(&format!("{}.color", field), color)?,
+    None => Rgba([255, 255, 255, 255]),
+  };
+  let channel_distance = |name: &str, value: Option<f64>, default: f64| match value {
+    None => Ok(default as f32),
+    Some(value) if (0.0..=255.0).contains(&value) => Ok(value as f32),
+    Some(value) => Err(Error::new(
+      ErrorCode::InvalidRemoveBackground,
+      format!(
+        "{}.{} must be between 0 and 255, got {}",
+        field, name, value
+      ),
+    )),
+  };
+
+  Ok(BackgroundRemoval {
+    color,
+    tolerance: channel_distance("tolerance", options.tolerance, DEFAULT_KEY_TOLERANCE)?,
+    feather: channel_distance("feather", options.feather, DEFAULT_KEY_FEATHER)?,
+    mode: options.mode.unwrap_or(BackgroundRemovalMode::Flood),
+  })
+}
+
+fn parse_auto_trim(field: &str, options: &AutoTrimOptions) -> Result<AutoTrim, ErrorCode> {
+  let tolerance = options.tolerance.unwrap_or(DEFAULT_TRIM_TOLERANCE);
+  if tolerance.fract() != 0.0 || !(0.0..=255.0).contains(&tolerance) {
+    return Err(Error::new(
+      ErrorCode::InvalidAutoTrim,
+      format!(
+        "{}.tolerance must be a whole number between 0 and 255, got {}",
+        field, tolerance
+      ),
+    ));
+  }
+  Ok(AutoTrim {
+    tolerance: tolerance as u8,
+  })
+}
+
+fn parse_shadow(field: &str, options: &ShadowOptions) -> Result<Shadow, ErrorCode> {
+  let blur_radius = options.blur_radius.unwrap_or(DEFAULT_SHADOW_BLUR_RADIUS);
+  if !(0.0..=MAX_SHADOW_BLUR_RADIUS).contains(&blur_radius) {
+    return Err(Error::new(
+      ErrorCode::InvalidShadow,
+      format!(
+        "{}.blurRadius must be between 0 and {}, got {}",
+        field, MAX_SHADOW_BLUR_RADIUS, blur_radius
+      ),
+    ));
+  }
+
+  let color = match &options.color {
+    Some(color) => parse_color(&format!("{}.color", field), color)?,
+    None => Rgba([0, 0, 0, 255]),
+  };
+
+  Ok(Shadow {
+    offset_x: options.offset_x.unwrap_or(0).into(),
+    offset_y: options.offset_y.unwrap_or(0).into(),
+    blur_radius: blur_radius as f32,
+    color,
+    opacity: parse_opacity(
+      &format!("{}.opacity", field),
+      Some(options.opacity.unwrap_or(DEFAULT_SHADOW_OPACITY)),
+    )?,
+  })
+}
+
+fn parse_transform(field: &str, product: &ProductOptions) -> Result<Transform, ErrorCode> {
+  let rotate = product.rotate.unwrap_or(0.0);
+  if !rotate.is_finite() {
+    return Err(Error::new(
+      ErrorCode::InvalidTransform,
+      format!("{}.rotate must be a finite number, got {}", field, rotate),
+    ));
+  }
+
+  Ok(Transform {
+    rotate: rotate as f32,
+    flip_h: product.flip_h.unwrap_or(false),
+    flip_v: product.flip_v.unwrap_or(false),
+  })
+}
+
+fn parse_background(field: &str, background: &BackgroundOptions) -> Result<Background, ErrorCode> {
+  let missing = |name: &str, types: &str| {
+    Error::new(
+      ErrorCode::InvalidBackground,
+      format!("{}.{} is required for {}", field, name, types),
+    )
+  };
+  let color = || {
+    background
+      .color
+      .as_ref()
+      .map(|color| parse_color(&format!("{}.color", field), color))
+      .transpose()
+  };
+  let stops = || {
+    let stops = background
+      .stops
+      .as_ref()
+      .filter(|stops| !stops.is_empty())
+      .ok_or_else(|| missing("stops", "LinearGradient and RadialGradient"))?;
+    parse_gradient_stops(&format!("{}.stops", field), stops)
+  };
+
+  Ok(match background.r#type {
+    BackgroundType::Solid => Background::Solid(color()?.ok_or_else(|| missing("color", "Solid"))?),
+    BackgroundType::LinearGradient => {
+      let angle = background.angle.unwrap_or(DEFAULT_GRADIENT_ANGLE);
+      if !angle.is_finite() {
+        return Err(Error::new(
+          ErrorCode::InvalidBackground,
+          format!("{}.angle must be a finite number, got {}", field, angle),
+        ));
+      }
+      Background::LinearGradient {
+        stops: stops()?,
+        angle: angle as f32,
+      }
+    }
+    BackgroundType::RadialGradient => Background::RadialGradient { stops: stops()? },
+    BackgroundType::Image => Background::Image {
+      buffer: background
+        .buffer
+        .clone()
+        .ok_or_else(|| missing("buffer", "Image"))?,
+      fit: background.fit.unwrap_or(BackgroundFit::Cover),
+      color: color()?.unwrap_or(Rgba([0, 0, 0, 0])),
+    },
+  })
+}
+
+fn parse_gradient_stops(
+  field: &str,
+  stops: &[GradientStopOptions],
+) -> Result<Vec<GradientStop>, ErrorCode> {
+  let mut parsed = stops
+    .iter()
+    .enumerate()
+    .map(|(index, stop)| {
+      if !(0.0..=1.0).contains(&stop.offset) {
+        return Err(Error::new(
+          ErrorCode::InvalidBackground,
+          format!(
+            "{}[{}].offset must be between 0 and 1, got {}",
+            field, index, stop.offset
+          ),
+        ));
+      }
+      Ok(GradientStop {
+        offset: stop.offset as f32,
+        color: parse_color(&format!("{}[{}].color", field, index), &stop.color)?,
+      })
+    })
+    .collect::<Result<Vec<_>, ErrorCode>>()?;
+  parsed.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+  Ok(parsed)
+}
+
+fn parse_placement(
+  field: &str,
+  offset_mode: Option<&OffsetModeOptions>,
+) -> Result<Placement, ErrorCode> {
+  let offset_mode = match offset_mode {
+    Some(offset_mode) => offset_mode,
+    None => {
+      return Ok(Placement {
+        offset: OffsetMode::Center,
+        anchor: Anchor::Center,
+        clamp: false,
+      })
+    }
+  };
+
+  let offset = match offset_mode.r#type {
+    OffsetModeType::Pixel => {
+      let [x, y] = parse_offset_value(field, offset_mode.value.as_deref())?;
+      OffsetMode::Pixel(x as i64, y as i64)
+    }
+    OffsetModeType::Percent => {
+      let [x, y] = parse_offset_value(field, offset_mode.value.as_deref())?;
+      OffsetMode::Percent(x as f32, y as f32)
+    }
+    OffsetModeType::Center => OffsetMode::Center,
+  };
+  Ok(Placement {
+    offset,
+    anchor: offset_mode.anchor.unwrap_or(Anchor::Center),
+    clamp: offset_mode.clamp.unwrap_or(false),
+  })
+}
+
+fn parse_color(field: &str, color: &Either<String, Vec<f64>>) -> Result<Rgba<u8>, ErrorCode> {
+  parse_color_input(color)
+    .map_err(|reason| Error::new(ErrorCode::InvalidColor, format!("{} {}", field, reason)))
+}
+
+fn parse_offset_value(field: &str, value: Option<&[f64]>) -> Result<[f64; 2], ErrorCode> {
+  let invalid = |reason: String| Error::new(ErrorCode::InvalidOffsetMode, reason);
+
+  let value =
+    value.ok_or_else(|| invalid(format!("{}.value is required for Pixel and Percent", field)))?;
+  let [x, y]: [f64; 2] = value.try_into().map_err(|_| {
+    invalid(format!(
+      "{}.value must have 2 entries [x, y], got {}",
+      field,
+      value.len()
+    ))
+  })?;
+  if !(-MAX_OFFSET..=MAX_OFFSET).contains(&x) || !(-MAX_OFFSET..=MAX_OFFSET).contains(&y) {
+    return Err(invalid(format!(
+      "{}.value must be numbers between -{} and {}, got [{}, {}]",
+      field, MAX_OFFSET, MAX_OFFSET, x, y
+    )));
+  }
+  Ok([x, y])
+}
+
+// Sizes arrive as JS numbers, so negative and fractional values are caught
+// here rather than wrapped or truncated by the conversion to `u32`.
+fn parse_dimension(field: &str, pixels: f64, code: ErrorCode) -> Result<u32, ErrorCode> {
+  if pixels.fract() != 0.0 || !(1.0..=u32::MAX as f64).contains(&pixels) {
+    return Err(Error::new(
+      code,
+      format!(
+        "{} must be a whole number of pixels between 1 and {}, got {}",
+        field,
+        u32::MAX,
+        pixels
+      ),
+    ));
+  }
+  Ok(pixels as u32)
+}
+
+fn parse_opacity(field: &str, opacity: Option<f64>) -> Result<f32, ErrorCode> {
+  match opacity {
+    None => Ok(1.0),
+    Some(opacity) if (0.0..=1.0).contains(&opacity) => Ok(opacity as f32),
+    Some(opacity) => Err(Error::new(
+      ErrorCode::InvalidOpacity,
+      format!("{} must be between 0 and 1, got {}", field, opacity),
+    )),
+  }
+}
@@ -0,0 +1,31 @@
+use std::io::Cursor;
+
+use exif::{In, Tag};
+use image::DynamicImage;
+
+/// Rotates or flips `image` so it displays upright according to the EXIF
+/// Orientation tag in `buffer`. Images without the tag are returned as is.
+pub fn auto_orient(image: DynamicImage, buffer: &[u8]) -> DynamicImage {
+  match read_orientation(buffer) {
+    Some(2) => image.fliph(),
+    Some(3) => image.rotate180(),
+    Some(4) => image.flipv(),
+    Some(5) => image.rotate90().fliph(),
+    Some(6) => image.rotate90(),
+    Some(7) => image.rotate270().fliph(),
+    Some(8) => image.rotate270(),
+    _ => image,
+  }
+}
+
+// A broken EXIF block shouldn't fail an image that decoded fine, so any
+// error reads as "no orientation".
+fn read_orientation(buffer: &[u8]) -> Option<u32> {
+  let exif = exif::Reader::new()
+    .read_from_container(&mut Cursor::new(buffer))
+    .ok()?;
+  exif
+    .get_field(Tag::Orientation, In::PRIMARY)?
+    .value
+    .get_uint(0)
+}
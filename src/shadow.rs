@@ -0,0 +1,47 @@
+use image::{Rgba, RgbaImage};
+
+#[derive(Clone, Copy)]
+pub struct Shadow {
+  pub offset_x: i64,
+  pub offset_y: i64,
+  /// Blur radius in pixels, the gaussian sigma is half of it as in CSS.
+  pub blur_radius: f32,
+  pub color: Rgba<u8>,
+  pub opacity: f32,
+}
+
+impl Shadow {
+  /// Margin added around the layer so the blur isn't clipped. `blur` samples
+  /// out to two sigmas, which is the radius.
+  pub fn padding(&self) -> u32 {
+    self.blur_radius.ceil() as u32
+  }
+}
+
+/// Builds the blurred shadow of `layer` from its alpha channel. The result is
+/// [`Shadow::padding`] pixels larger than the layer on every side.
+pub fn render_shadow(layer: &RgbaImage, shadow: &Shadow) -> RgbaImage {
+  let padding = shadow.padding();
+  let (width, height) = layer.dimensions();
+  let Rgba([red, green, blue, alpha]) = shadow.color;
+
+  let mut silhouette = RgbaImage::from_pixel(
+    width + padding * 2,
+    height + padding * 2,
+    Rgba([red, green, blue, 0]),
+  );
+  for (x, y, pixel) in layer.enumerate_pixels() {
+    let coverage = (pixel[3] as u32 * alpha as u32 + 127) / 255;
+    silhouette.put_pixel(
+      x + padding,
+      y + padding,
+      Rgba([red, green, blue, coverage as u8]),
+    );
+  }
+
+  if shadow.blur_radius > 0.0 {
+    image::imageops::blur(&silhouette, shadow.blur_radius / 2.0)
+  } else {
+    silhouette
+  }
+}
@@ -0,0 +1,56 @@
+use std::marker::PhantomData;
+
+use napi::bindgen_prelude::*;
+use napi::{Env, Error, JsError, Result};
+
+use crate::{Composite, CompositeJob, ErrorCode};
+
+/// Runs a [`CompositeJob`] on the libuv threadpool.
+///
+/// Option errors are deferred to `compute` so they reject the promise rather
+/// than throwing synchronously. `T` is what the promise resolves to.
+pub struct CompositeTask<T> {
+  job: Result<CompositeJob, ErrorCode>,
+  result: Option<Result<Composite, ErrorCode>>,
+  output: PhantomData<fn() -> T>,
+}
+
+impl<T> CompositeTask<T> {
+  pub fn new(job: Result<CompositeJob, ErrorCode>) -> Self {
+    CompositeTask {
+      job,
+      result: None,
+      output: PhantomData,
+    }
+  }
+}
+
+impl<T> Task for CompositeTask<T>
+where
+  T: From<Composite> + ToNapiValue + TypeName,
+{
+  // The result is kept on the task rather than returned as `Output`: when the
+  // signal aborts before `compute` runs, napi still calls `resolve` with a
+  // zeroed `Output`, which must not hold anything that owns memory.
+  // `compute` can only fail with a plain `Status`, so the coded error is
+  // carried through to `resolve` where an `Env` is available to build it.
+  type Output = ();
+  type JsValue = T;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    self.result = Some(match &self.job {
+      Ok(job) => job.run(),
+      Err(err) => Err(err.clone()),
+    });
+    Ok(())
+  }
+
+  fn resolve(&mut self, env: Env, _output: Self::Output) -> Result<Self::JsValue> {
+    match self.result.take() {
+      Some(Ok(composite)) => Ok(composite.into()),
+      Some(Err(err)) => Err(Error::from(JsError::from(err).into_unknown(env))),
+      // Aborted, the promise is already rejected with an `AbortError`.
+      None => Err(Error::new(Status::Cancelled, "The task was aborted")),
+    }
+  }
+}
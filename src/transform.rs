@@ -0,0 +1,106 @@
+use image::{imageops, Rgba, RgbaImage};
+
+/// Mirroring and rotation applied to a layer after resizing.
+#[derive(Clone, Copy, Default)]
+pub struct Transform {
+  /// Degrees clockwise, as in CSS.
+  pub rotate: f32,
+  pub flip_h: bool,
+  pub flip_v: bool,
+}
+
+impl Transform {
+  /// Size of a `size` image once transformed. Rotation grows the canvas to
+  /// the rotated bounds.
+  pub fn output_size(&self, (width, height): (u32, u32)) -> (u32, u32) {
+    match right_angles(self.rotate) {
+      Some(1) | Some(3) => (height, width),
+      Some(_) => (width, height),
+      None => {
+        let (sin, cos) = self.rotate.to_radians().sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        // The tolerance keeps float error from adding a transparent row.
+        let bound = |a: u32, b: u32| ((a as f32 * cos + b as f32 * sin) - 1e-3).ceil().max(1.0);
+        (bound(width, height) as u32, bound(height, width) as u32)
+      }
+    }
+  }
+}
+
+/// Flips, then rotates `image` around its center.
+pub fn apply_transform(mut image: RgbaImage, transform: &Transform) -> RgbaImage {
+  if transform.flip_h {
+    imageops::flip_horizontal_in_place(&mut image);
+  }
+  if transform.flip_v {
+    imageops::flip_vertical_in_place(&mut image);
+  }
+
+  match right_angles(transform.rotate) {
+    Some(0) => image,
+    Some(1) => imageops::rotate90(&image),
+    Some(2) => imageops::rotate180(&image),
+    Some(3) => imageops::rotate270(&image),
+    _ => rotate(&image, transform),
+  }
+}
+
+// Multiples of 90 degrees are exact pixel moves, not resampled.
+fn right_angles(degrees: f32) -> Option<u32> {
+  let turns = degrees.rem_euclid(360.0) / 90.0;
+  (turns.fract() == 0.0).then_some(turns as u32 % 4)
+}
+
+// Maps every output pixel back into the source and samples it bilinearly,
+// with transparent pixels outside the source.
+fn rotate(image: &RgbaImage, transform: &Transform) -> RgbaImage {
+  let (width, height) = image.dimensions();
+  let (out_width, out_height) = transform.output_size((width, height));
+  let (sin, cos) = transform.rotate.to_radians().sin_cos();
+
+  RgbaImage::from_fn(out_width, out_height, |x, y| {
+    let dx = x as f32 + 0.5 - out_width as f32 / 2.0;
+    let dy = y as f32 + 0.5 - out_height as f32 / 2.0;
+    let source_x = dx * cos + dy * sin + width as f32 / 2.0 - 0.5;
+    let source_y = dy * cos - dx * sin + height as f32 / 2.0 - 0.5;
+    sample_bilinear(image, source_x, source_y)
+  })
+}
+
+// Interpolates premultiplied, like resizing, so transparent pixels don't
+// darken the edges.
+fn sample_bilinear(image: &RgbaImage, x: f32, y: f32) -> Rgba<u8> {
+  let (left, top) = (x.floor(), y.floor());
+  let (fx, fy) = (x - left, y - top);
+
+  let mut premultiplied = [0.0f32; 3];
+  let mut alpha = 0.0f32;
+  for (offset_x, offset_y, weight) in [
+    (0, 0, (1.0 - fx) * (1.0 - fy)),
+    (1, 0, fx * (1.0 - fy)),
+    (0, 1, (1.0 - fx) * fy),
+    (1, 1, fx * fy),
+  ] {
+    let (sx, sy) = (left as i64 + offset_x, top as i64 + offset_y);
+    if sx < 0 || sy < 0 || sx >= image.width() as i64 || sy >= image.height() as i64 {
+      continue;
+    }
+    let pixel = image.get_pixel(sx as u32, sy as u32);
+    let pixel_alpha = pixel[3] as f32 * weight;
+    for (channel, total) in premultiplied.iter_mut().enumerate() {
+      *total += pixel[channel] as f32 * pixel_alpha;
+    }
+    alpha += pixel_alpha;
+  }
+
+  if alpha <= 0.0 {
+    return Rgba([0, 0, 0, 0]);
+  }
+  let color = |total: f32| (total / alpha).round().clamp(0.0, 255.0) as u8;
+  Rgba([
+    color(premultiplied[0]),
+    color(premultiplied[1]),
+    color(premultiplied[2]),
+    alpha.round().clamp(0.0, 255.0) as u8,
+  ])
+}
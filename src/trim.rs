@@ -0,0 +1,59 @@
+use image::{Rgba, RgbaImage};
+
+/// Region of the decoded image kept by `autoTrim`, in source pixels.
+#[napi(object)]
+#[derive(Clone, Copy)]
+pub struct TrimBox {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+#[derive(Clone, Copy)]
+pub struct AutoTrim {
+  /// Largest per-channel difference from the border color still trimmed away.
+  pub tolerance: u8,
+}
+
+/// Bounding box of everything that is neither transparent nor the color of
+/// the top-left pixel. The whole image when there is nothing to trim to.
+pub fn find_trim_box(image: &RgbaImage, trim: &AutoTrim) -> TrimBox {
+  let (width, height) = image.dimensions();
+  let whole = TrimBox {
+    x: 0,
+    y: 0,
+    width,
+    height,
+  };
+  if width == 0 || height == 0 {
+    return whole;
+  }
+
+  let border = *image.get_pixel(0, 0);
+  let mut bounds: Option<(u32, u32, u32, u32)> = None;
+  for (x, y, pixel) in image.enumerate_pixels() {
+    if is_background(pixel, &border, trim.tolerance) {
+      continue;
+    }
+    bounds = Some(match bounds {
+      Some((left, top, right, bottom)) => (left.min(x), top.min(y), right.max(x), bottom.max(y)),
+      None => (x, y, x, y),
+    });
+  }
+
+  match bounds {
+    Some((left, top, right, bottom)) => TrimBox {
+      x: left,
+      y: top,
+      width: right - left + 1,
+      height: bottom - top + 1,
+    },
+    None => whole,
+  }
+}
+
+fn is_background(pixel: &Rgba<u8>, border: &Rgba<u8>, tolerance: u8) -> bool {
+  pixel[3] <= tolerance
+    || (0..4).all(|channel| pixel[channel].abs_diff(border[channel]) <= tolerance)
+}